@@ -0,0 +1,202 @@
+//! Unattended timing of every scene in every render mode.
+use amethyst::{
+    core::{
+        ecs::{Read, World, WorldExt, Write, WriteStorage},
+        Transform,
+    },
+    renderer::ActiveCamera,
+    utils::auto_fov::AutoFov,
+    Error,
+};
+use serde::Serialize;
+use std::{fmt::Write as _, fs, path::PathBuf, time::Instant};
+
+use crate::{
+    camera_path::CameraPathPlayer,
+    perf::{self, FrameStats},
+    scenes::SceneMap,
+    ui::UIState,
+    RenderMode,
+};
+
+pub const DEFAULT_WARMUP: u32 = 60;
+pub const DEFAULT_FRAMES: u32 = 300;
+
+#[derive(Clone, Debug)]
+pub struct BenchmarkOptions {
+    /// Report file, written as JSON if it ends in `.json` and as CSV otherwise.
+    pub output: PathBuf,
+    /// Frames rendered before measuring each configuration.
+    pub warmup: u32,
+    /// Frames measured per configuration.
+    pub frames: u32,
+}
+
+/// Statistics of one scene and render mode, frame times in milliseconds.
+#[derive(Clone, Debug, Serialize)]
+pub struct BenchmarkResult {
+    pub scene: String,
+    pub mode: &'static str,
+    pub frames: usize,
+    /// Whether the scene's camera path was played, otherwise the camera stayed at the scene's pose.
+    pub camera_path: bool,
+    pub min_ms: f32,
+    pub avg_ms: f32,
+    pub max_ms: f32,
+    pub p99_ms: f32,
+    pub avg_fps: f32,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct BenchmarkReport<'a> {
+    warmup: u32,
+    frames: u32,
+    results: &'a [BenchmarkResult],
+}
+
+/// Steps through all scene and render mode combinations, one frame per `update`.
+///
+/// Each configuration starts from the scene's camera pose, renders `warmup` frames and then
+/// measures `frames` frames of wall-clock time while the scene's camera path plays from the
+/// start. Run it with fixed stepping so the camera passes the same views in every build.
+pub struct Benchmark {
+    options: BenchmarkOptions,
+    configs: Vec<(usize, RenderMode)>,
+    current: usize,
+    frame: u32,
+    last_frame: Option<Instant>,
+    samples: Vec<f32>,
+    camera_path: bool,
+    results: Vec<BenchmarkResult>,
+}
+
+impl Benchmark {
+    pub fn new(options: BenchmarkOptions) -> Self {
+        Self {
+            options,
+            configs: Vec::new(),
+            current: 0,
+            frame: 0,
+            last_frame: None,
+            samples: Vec::new(),
+            camera_path: false,
+            results: Vec::new(),
+        }
+    }
+
+    /// Called once per frame after loading finished; returns `true` once the report is written.
+    pub fn update(&mut self, world: &mut World) -> Result<bool, Error> {
+        if self.configs.is_empty() {
+            let scenes = world.read_resource::<SceneMap>().len();
+            self.configs = (0..scenes)
+                .flat_map(|scene| RenderMode::ALL.iter().map(move |mode| (scene, *mode)))
+                .collect();
+        }
+        let now = Instant::now();
+        let frame_time = self.last_frame.replace(now).map(|last| perf::millis(now - last));
+
+        let (scene, mode) = match self.configs.get(self.current) {
+            Some(config) => *config,
+            None => {
+                self.write_report()?;
+                return Ok(true);
+            }
+        };
+
+        if self.frame == 0 {
+            self.start(world, scene, mode);
+        } else if self.frame > self.options.warmup {
+            if let Some(frame_time) = frame_time {
+                self.samples.push(frame_time);
+            }
+        }
+        if self.frame == self.options.warmup {
+            let mut player = world.write_resource::<CameraPathPlayer>();
+            self.camera_path = !player.path.keyframes.is_empty();
+            player.time = 0.0;
+            player.playing = self.camera_path;
+            player.looping = true;
+        }
+        self.frame += 1;
+
+        if self.samples.len() >= self.options.frames as usize {
+            self.finish(world, scene, mode);
+        }
+        Ok(false)
+    }
+
+    /// Switches to a configuration and puts the camera at the scene's pose.
+    fn start(&mut self, world: &mut World, scene: usize, mode: RenderMode) {
+        world.write_resource::<UIState>().scene = scene;
+        *world.write_resource::<RenderMode>() = mode;
+        world.exec(
+            |(scene_map, camera, mut player, mut transforms, mut auto_fovs): (
+                Read<'_, SceneMap>,
+                Read<'_, ActiveCamera>,
+                Write<'_, CameraPathPlayer>,
+                WriteStorage<'_, Transform>,
+                WriteStorage<'_, AutoFov>,
+            )| {
+                player.playing = false;
+                let entry = match scene_map.get(scene) {
+                    Some(entry) => entry,
+                    None => return,
+                };
+                println!("Benchmarking {} in {} mode.", entry.info.name, mode.name());
+                if let Some(camera) = camera.entity {
+                    if let (Some(transform), Some(auto_fov)) = (transforms.get_mut(camera), auto_fovs.get_mut(camera)) {
+                        entry.info.camera.apply(transform, auto_fov);
+                    }
+                }
+            },
+        );
+    }
+
+    fn finish(&mut self, world: &mut World, scene: usize, mode: RenderMode) {
+        let stats = FrameStats::new(&self.samples);
+        let name = world
+            .read_resource::<SceneMap>()
+            .get(scene)
+            .map_or_else(String::new, |entry| entry.info.name.clone());
+        self.results.push(BenchmarkResult {
+            scene: name,
+            mode: mode.name(),
+            frames: self.samples.len(),
+            camera_path: self.camera_path,
+            min_ms: stats.min,
+            avg_ms: stats.avg,
+            max_ms: stats.max,
+            p99_ms: stats.p99,
+            avg_fps: if stats.avg > 0.0 { 1000.0 / stats.avg } else { 0.0 },
+        });
+        world.write_resource::<CameraPathPlayer>().playing = false;
+        self.samples.clear();
+        self.frame = 0;
+        self.current += 1;
+    }
+
+    fn write_report(&self) -> Result<(), Error> {
+        let output = &self.options.output;
+        let contents = if output.extension().map_or(false, |ext| ext == "json") {
+            let report = BenchmarkReport {
+                warmup: self.options.warmup,
+                frames: self.options.frames,
+                results: &self.results,
+            };
+            serde_json::to_string_pretty(&report).map_err(|e| Error::from_string(e.to_string()))?
+        } else {
+            let mut csv = String::from("scene,mode,frames,camera_path,min_ms,avg_ms,max_ms,p99_ms,avg_fps\n");
+            for r in &self.results {
+                let _ = writeln!(
+                    csv,
+                    "{},{},{},{},{:.3},{:.3},{:.3},{:.3},{:.1}",
+                    r.scene, r.mode, r.frames, r.camera_path, r.min_ms, r.avg_ms, r.max_ms, r.p99_ms, r.avg_fps
+                );
+            }
+            csv
+        };
+        fs::write(output, contents)?;
+        println!("Wrote benchmark report to {}.", output.display());
+        Ok(())
+    }
+}
@@ -0,0 +1,322 @@
+//! Named camera viewpoints, stored per scene.
+use amethyst::{
+    core::{
+        ecs::{Read, System, SystemData, World, Write, WriteStorage},
+        shrev::{EventChannel, ReaderId},
+        SystemDesc, Transform,
+    },
+    input::InputEvent,
+    renderer::ActiveCamera,
+    utils::auto_fov::AutoFov,
+    winit::VirtualKeyCode,
+    Error,
+};
+use amethyst_imgui::imgui::ImString;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    filtered_input::FilteredInputEvent,
+    prefab_data::Scene,
+    scenes::{CameraView, SceneMap},
+};
+
+/// Location of the bookmark file inside the assets directory.
+pub const BOOKMARKS_PATH: &str = "config/bookmarks.ron";
+
+/// Keys jumping to the first nine bookmarks of the current scene.
+const HOTKEYS: [VirtualKeyCode; 9] = [
+    VirtualKeyCode::Key1,
+    VirtualKeyCode::Key2,
+    VirtualKeyCode::Key3,
+    VirtualKeyCode::Key4,
+    VirtualKeyCode::Key5,
+    VirtualKeyCode::Key6,
+    VirtualKeyCode::Key7,
+    VirtualKeyCode::Key8,
+    VirtualKeyCode::Key9,
+];
+
+/// A named viewpoint. The fields are kept apart from `CameraView` so the file layout stays the same.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Bookmark {
+    pub name: String,
+    pub position: [f32; 3],
+    /// Camera orientation as a quaternion, `[x, y, z, w]`.
+    pub rotation: [f32; 4],
+    /// Horizontal field of view in degrees.
+    pub fov: f32,
+}
+
+impl Bookmark {
+    fn new(name: String, view: CameraView) -> Self {
+        Self {
+            name,
+            position: view.position,
+            rotation: view.rotation,
+            fov: view.fov,
+        }
+    }
+
+    pub fn view(&self) -> CameraView {
+        CameraView {
+            position: self.position,
+            rotation: self.rotation,
+            fov: self.fov,
+        }
+    }
+}
+
+/// Layout of the bookmark file, keyed by scene name.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+struct BookmarkFile {
+    scenes: BTreeMap<String, Vec<Bookmark>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum BookmarkRequest {
+    Jump(usize),
+    Create(String),
+    Rename(usize, String),
+    /// Replaces the viewpoint of a bookmark with the current one.
+    Overwrite(usize),
+    Delete(usize),
+}
+
+/// Bookmarks of all scenes, plus the pending request from the UI or the number keys.
+#[derive(Debug, Default)]
+pub struct Bookmarks {
+    path: PathBuf,
+    /// Cleared when the file exists but could not be read, so it is not overwritten with an empty list.
+    writable: bool,
+    scenes: BTreeMap<String, Vec<Bookmark>>,
+    pub request: Option<BookmarkRequest>,
+    pub status: String,
+}
+
+impl Bookmarks {
+    /// Reads the bookmark file, starting empty if it does not exist yet.
+    ///
+    /// Fails on a field of view outside of 0 to 180 degrees rather than applying it to the camera.
+    pub fn load(assets_dir: &Path) -> Result<Self, Error> {
+        let path = assets_dir.join(BOOKMARKS_PATH);
+        let file = if path.exists() {
+            ron::de::from_str::<BookmarkFile>(&fs::read_to_string(&path)?)?
+        } else {
+            BookmarkFile::default()
+        };
+        for (scene, bookmarks) in &file.scenes {
+            if let Some(bookmark) = bookmarks.iter().find(|bookmark| !(bookmark.fov > 0.0 && bookmark.fov < 180.0)) {
+                return Err(Error::from_string(format!(
+                    "bookmark `{}` of {} has an invalid field of view of {}",
+                    bookmark.name, scene, bookmark.fov
+                )));
+            }
+        }
+        Ok(Self {
+            path,
+            writable: true,
+            scenes: file.scenes,
+            request: None,
+            status: String::new(),
+        })
+    }
+
+    /// Bookmarks that keep `path` untouched after it failed to load.
+    fn unreadable(path: PathBuf, error: &Error) -> Self {
+        Self {
+            status: format!(
+                "Failed to read {}: {}. Fix or remove it, bookmarks are not saved until then.",
+                path.display(),
+                error
+            ),
+            path,
+            ..Default::default()
+        }
+    }
+
+    fn save(&self) -> Result<(), Error> {
+        if !self.writable {
+            return Err(Error::from_string("the file could not be read at startup, so it is not overwritten"));
+        }
+        let file = BookmarkFile {
+            scenes: self.scenes.clone(),
+        };
+        fs::write(&self.path, ron::ser::to_string_pretty(&file, PrettyConfig::default())?)?;
+        Ok(())
+    }
+
+    pub fn scene(&self, scene: &str) -> &[Bookmark] {
+        self.scenes.get(scene).map_or(&[], |bookmarks| bookmarks.as_slice())
+    }
+}
+
+/// Jumps to bookmarks on the number keys and draws the bookmark list of the current scene.
+pub struct BookmarkSystem {
+    event_reader: ReaderId<FilteredInputEvent>,
+    name: ImString,
+}
+
+impl<'a> System<'a> for BookmarkSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Read<'a, EventChannel<FilteredInputEvent>>,
+        Write<'a, Bookmarks>,
+        Read<'a, Scene>,
+        Read<'a, SceneMap>,
+        Read<'a, ActiveCamera>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, AutoFov>,
+    );
+
+    fn run(
+        &mut self,
+        (events, mut bookmarks, scene, scene_map, camera, mut transforms, mut auto_fovs): Self::SystemData,
+    ) {
+        use amethyst_imgui::imgui::*;
+
+        for event in events.read(&mut self.event_reader) {
+            if let FilteredInputEvent::Free(InputEvent::KeyPressed { key_code, .. }) = *event {
+                if let Some(index) = HOTKEYS.iter().position(|key| *key == key_code) {
+                    bookmarks.request = Some(BookmarkRequest::Jump(index));
+                }
+            }
+        }
+
+        let scene_name = match scene.scene.and_then(|index| scene_map.get(index)) {
+            Some(entry) => entry.info.name.clone(),
+            None => return,
+        };
+        let camera = match camera.entity {
+            Some(camera) => camera,
+            None => return,
+        };
+
+        let labels = bookmarks
+            .scene(&scene_name)
+            .iter()
+            .enumerate()
+            .map(|(index, bookmark)| match HOTKEYS.get(index) {
+                Some(_) => im_str!("{}: {}", index + 1, bookmark.name),
+                None => ImString::new(bookmark.name.as_str()),
+            })
+            .collect::<Vec<_>>();
+        let mut request = None;
+        amethyst_imgui::with(|ui| {
+            Window::new(im_str!("Camera bookmarks"))
+                .size([280.0, 200.0], Condition::FirstUseEver)
+                .build(ui, || {
+                    ui.input_text(im_str!("Name"), &mut self.name).build();
+                    if ui.small_button(im_str!("Add current view")) {
+                        let name = match self.name.to_str() {
+                            "" => format!("View {}", labels.len() + 1),
+                            name => name.to_owned(),
+                        };
+                        request = Some(BookmarkRequest::Create(name));
+                    }
+                    ui.separator();
+                    for (index, label) in labels.iter().enumerate() {
+                        let id = ui.push_id(index as i32);
+                        if ui.small_button(im_str!("Go")) {
+                            request = Some(BookmarkRequest::Jump(index));
+                        }
+                        ui.same_line(0.0);
+                        ui.text(label);
+                        ui.same_line(0.0);
+                        if ui.small_button(im_str!("Overwrite")) {
+                            request = Some(BookmarkRequest::Overwrite(index));
+                        }
+                        ui.same_line(0.0);
+                        if ui.small_button(im_str!("Rename")) && !self.name.to_str().is_empty() {
+                            request = Some(BookmarkRequest::Rename(index, self.name.to_str().to_owned()));
+                        }
+                        ui.same_line(0.0);
+                        if ui.small_button(im_str!("Delete")) {
+                            request = Some(BookmarkRequest::Delete(index));
+                        }
+                        id.pop(ui);
+                    }
+                    if !bookmarks.status.is_empty() {
+                        ui.text_disabled(&ImString::new(bookmarks.status.as_str()));
+                    }
+                });
+        });
+
+        let request = match request.or_else(|| bookmarks.request.take()) {
+            Some(request) => request,
+            None => return,
+        };
+        if let BookmarkRequest::Jump(index) = request {
+            if let (Some(bookmark), Some(transform)) =
+                (bookmarks.scene(&scene_name).get(index), transforms.get_mut(camera))
+            {
+                bookmark.view().apply(transform, auto_fovs.get_mut(camera));
+            }
+            return;
+        }
+
+        let view = match transforms.get(camera) {
+            Some(transform) => CameraView::capture(transform, auto_fovs.get(camera)),
+            None => return,
+        };
+        let scene_bookmarks = bookmarks.scenes.entry(scene_name).or_insert_with(Vec::new);
+        match request {
+            BookmarkRequest::Create(name) => scene_bookmarks.push(Bookmark::new(name, view)),
+            BookmarkRequest::Rename(index, name) => {
+                if let Some(bookmark) = scene_bookmarks.get_mut(index) {
+                    bookmark.name = name;
+                }
+            }
+            BookmarkRequest::Overwrite(index) => {
+                if let Some(bookmark) = scene_bookmarks.get_mut(index) {
+                    *bookmark = Bookmark::new(bookmark.name.clone(), view);
+                }
+            }
+            BookmarkRequest::Delete(index) => {
+                if index < scene_bookmarks.len() {
+                    scene_bookmarks.remove(index);
+                }
+            }
+            BookmarkRequest::Jump(_) => {}
+        }
+        bookmarks.status = match bookmarks.save() {
+            Ok(()) => String::new(),
+            Err(e) => format!("Failed to write {}: {}", bookmarks.path.display(), e),
+        };
+    }
+}
+
+pub struct BookmarkSystemDesc {
+    assets_dir: PathBuf,
+}
+
+impl BookmarkSystemDesc {
+    pub fn new(assets_dir: PathBuf) -> Self {
+        Self { assets_dir }
+    }
+}
+
+impl<'a, 'b> SystemDesc<'a, 'b, BookmarkSystem> for BookmarkSystemDesc {
+    fn build(self, world: &mut World) -> BookmarkSystem {
+        <BookmarkSystem as System<'_>>::SystemData::setup(world);
+
+        let bookmarks = Bookmarks::load(&self.assets_dir).unwrap_or_else(|e| {
+            let bookmarks = Bookmarks::unreadable(self.assets_dir.join(BOOKMARKS_PATH), &e);
+            println!("{}", bookmarks.status);
+            bookmarks
+        });
+        world.insert(bookmarks);
+        let event_reader = Write::<EventChannel<FilteredInputEvent>>::fetch(world).register_reader();
+
+        BookmarkSystem {
+            event_reader,
+            name: ImString::with_capacity(64),
+        }
+    }
+}
@@ -0,0 +1,422 @@
+//! Keyframed camera flights, replayed the same way on every run.
+use amethyst::{
+    core::{
+        ecs::{Read, System, Write, WriteStorage},
+        math::Vector3,
+        Time, Transform,
+    },
+    renderer::ActiveCamera,
+    utils::auto_fov::AutoFov,
+    Error,
+};
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    prefab_data::Scene,
+    scenes::{CameraView, SceneInfo, SceneMap, PREFAB_DIR},
+};
+
+/// Camera paths are stored next to the scene prefab as `<prefab>.camera.ron`.
+pub const CAMERA_PATH_SUFFIX: &str = ".camera.ron";
+
+/// Seconds between the keyframes sampled while recording.
+const RECORD_INTERVAL: f32 = 0.25;
+/// Seconds between the last keyframe and one added by hand.
+const KEYFRAME_SPACING: f32 = 2.0;
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub struct Keyframe {
+    /// Seconds from the start of the path.
+    pub time: f32,
+    pub view: CameraView,
+}
+
+/// Keyframes sorted by time.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CameraPath {
+    pub keyframes: Vec<Keyframe>,
+}
+
+impl CameraPath {
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |keyframe| keyframe.time)
+    }
+
+    /// View at `time`, held at the first and last keyframe outside of the path.
+    ///
+    /// Positions and FOV follow a Catmull-Rom spline through the keyframes, rotations are slerped.
+    pub fn sample(&self, time: f32) -> Option<CameraView> {
+        let keyframes = &self.keyframes;
+        let last = keyframes.len().checked_sub(1)?;
+        let next = match keyframes.iter().position(|keyframe| keyframe.time > time) {
+            Some(0) => return Some(keyframes[0].view),
+            Some(next) => next,
+            None => return Some(keyframes[last].view),
+        };
+        let (from, to) = (&keyframes[next - 1], &keyframes[next]);
+        let before = &keyframes[next.saturating_sub(2)].view;
+        let after = &keyframes[(next + 1).min(last)].view;
+        let interval = to.time - from.time;
+        if interval <= 0.0 {
+            return Some(to.view);
+        }
+        let t = (time - from.time) / interval;
+
+        let position = |view: &CameraView| Vector3::from(view.position);
+        let rotation = from.view.rotation();
+        let rotation = rotation.try_slerp(&to.view.rotation(), t, 1.0e-6).unwrap_or(rotation);
+        Some(CameraView::new(
+            catmull_rom(position(before), position(&from.view), position(&to.view), position(after), t),
+            rotation,
+            catmull_rom(before.fov, from.view.fov, to.view.fov, after.fov, t),
+        ))
+    }
+
+    /// Sorts hand edited files by time and drops all but the first of keyframes sharing a time,
+    /// so `sample` never interpolates over an empty interval.
+    fn normalize(&mut self) {
+        self.keyframes.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap());
+        self.keyframes.dedup_by(|keyframe, kept| keyframe.time <= kept.time);
+    }
+
+    /// Appends a keyframe, replacing any at or after `time`.
+    fn push(&mut self, time: f32, view: CameraView) {
+        self.keyframes.retain(|keyframe| keyframe.time < time);
+        self.keyframes.push(Keyframe { time, view });
+    }
+}
+
+/// Uniform Catmull-Rom spline between `p1` and `p2`.
+fn catmull_rom<T>(p0: T, p1: T, p2: T, p3: T, t: f32) -> T
+where
+    T: Copy + std::ops::Add<Output = T> + std::ops::Sub<Output = T> + std::ops::Mul<f32, Output = T>,
+{
+    let t2 = t * t;
+    let t3 = t2 * t;
+    (p1 * 2.0 + (p2 - p0) * t + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2 + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3)
+        * 0.5
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CameraPathRequest {
+    /// Appends the current view as a keyframe.
+    AddKeyframe,
+    Save,
+    Reload,
+    Clear,
+}
+
+/// Camera path of the current scene and its playback state.
+#[derive(Debug, Default)]
+pub struct CameraPathPlayer {
+    pub path: CameraPath,
+    /// Playback or recording position in seconds.
+    pub time: f32,
+    pub playing: bool,
+    pub looping: bool,
+    pub recording: bool,
+    pub request: Option<CameraPathRequest>,
+    pub status: String,
+}
+
+/// Records, plays back and stores the camera path of the current scene.
+///
+/// Playback advances with `Time`, so a fixed time step gives the same camera motion on every run.
+pub struct CameraPathSystem {
+    assets_dir: PathBuf,
+    /// Scene whose path is loaded.
+    loaded: Option<usize>,
+    since_sample: f32,
+}
+
+impl CameraPathSystem {
+    pub fn new(assets_dir: PathBuf) -> Self {
+        Self {
+            assets_dir,
+            loaded: None,
+            since_sample: 0.0,
+        }
+    }
+
+    fn path_file(&self, info: &SceneInfo) -> PathBuf {
+        if info.procedural {
+            self.assets_dir
+                .join(PREFAB_DIR)
+                .join(format!("{}{}", info.name, CAMERA_PATH_SUFFIX))
+        } else {
+            let stem = info.file_path.file_stem().unwrap_or_default().to_string_lossy();
+            info.file_path.with_file_name(format!("{}{}", stem, CAMERA_PATH_SUFFIX))
+        }
+    }
+}
+
+fn read_path(file: &Path) -> Result<CameraPath, Error> {
+    if !file.exists() {
+        return Ok(CameraPath::default());
+    }
+    let mut path: CameraPath = ron::de::from_str(&fs::read_to_string(file)?)?;
+    if let Some(keyframe) = path.keyframes.iter().find(|keyframe| !keyframe.time.is_finite()) {
+        return Err(Error::from_string(format!("Invalid keyframe time `{}`", keyframe.time)));
+    }
+    path.normalize();
+    Ok(path)
+}
+
+fn write_path(file: &Path, path: &CameraPath) -> Result<(), Error> {
+    fs::write(file, ron::ser::to_string_pretty(path, PrettyConfig::default())?)?;
+    Ok(())
+}
+
+impl<'a> System<'a> for CameraPathSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Write<'a, CameraPathPlayer>,
+        Read<'a, Time>,
+        Read<'a, Scene>,
+        Read<'a, SceneMap>,
+        Read<'a, ActiveCamera>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, AutoFov>,
+    );
+
+    fn run(&mut self, (mut player, time, scene, scene_map, camera, mut transforms, mut auto_fovs): Self::SystemData) {
+        use amethyst_imgui::imgui::*;
+
+        let (index, info) = match scene.scene.and_then(|index| Some((index, &scene_map.get(index)?.info))) {
+            Some(scene) => scene,
+            None => return,
+        };
+        let file = self.path_file(info);
+        if self.loaded != Some(index) {
+            self.loaded = Some(index);
+            player.request = Some(CameraPathRequest::Reload);
+        }
+
+        let duration = player.path.duration();
+        let mut was_recording = player.recording;
+        let mut scrubbed = false;
+        amethyst_imgui::with(|ui| {
+            Window::new(im_str!("Camera path"))
+                .size([300.0, 180.0], Condition::FirstUseEver)
+                .build(ui, || {
+                    ui.text(&im_str!("{} keyframes, {:.2} s", player.path.keyframes.len(), duration));
+                    let record_label = if player.recording { im_str!("Stop recording") } else { im_str!("Record") };
+                    if ui.small_button(record_label) {
+                        player.recording = !player.recording;
+                    }
+                    ui.same_line(0.0);
+                    let play_label = if player.playing { im_str!("Pause") } else { im_str!("Play") };
+                    if ui.small_button(play_label) && !player.recording {
+                        if !player.playing && player.time >= duration {
+                            player.time = 0.0;
+                        }
+                        player.playing = !player.playing;
+                    }
+                    ui.same_line(0.0);
+                    ui.checkbox(im_str!("Loop"), &mut player.looping);
+                    if duration > 0.0 && !player.recording {
+                        scrubbed = Slider::new(im_str!("Time"), 0.0..=duration).build(ui, &mut player.time);
+                    }
+                    if ui.small_button(im_str!("Add keyframe")) {
+                        player.request = Some(CameraPathRequest::AddKeyframe);
+                    }
+                    ui.same_line(0.0);
+                    if ui.small_button(im_str!("Clear")) {
+                        player.request = Some(CameraPathRequest::Clear);
+                    }
+                    if ui.small_button(im_str!("Save")) {
+                        player.request = Some(CameraPathRequest::Save);
+                    }
+                    ui.same_line(0.0);
+                    if ui.small_button(im_str!("Reload")) {
+                        player.request = Some(CameraPathRequest::Reload);
+                    }
+                    if !player.status.is_empty() {
+                        ui.text_disabled(&ImString::new(player.status.as_str()));
+                    }
+                });
+        });
+
+        let camera = camera.entity;
+        let current_view = camera.and_then(|camera| {
+            transforms
+                .get(camera)
+                .map(|transform| CameraView::capture(transform, auto_fovs.get(camera)))
+        });
+
+        match player.request.take() {
+            Some(CameraPathRequest::AddKeyframe) => {
+                if let Some(view) = current_view {
+                    let time = if player.path.keyframes.is_empty() { 0.0 } else { duration + KEYFRAME_SPACING };
+                    player.path.push(time, view);
+                    player.time = time;
+                }
+            }
+            Some(CameraPathRequest::Save) => {
+                player.status = match write_path(&file, &player.path) {
+                    Ok(()) => format!("Saved {}.", file.display()),
+                    Err(e) => format!("Failed to write {}: {}", file.display(), e),
+                };
+            }
+            Some(CameraPathRequest::Reload) => {
+                let (path, status) = match read_path(&file) {
+                    Ok(path) => (path, String::new()),
+                    Err(e) => (CameraPath::default(), format!("Failed to read {}: {}", file.display(), e)),
+                };
+                player.path = path;
+                player.status = status;
+                player.time = 0.0;
+                player.playing = false;
+                player.recording = false;
+                was_recording = false;
+            }
+            Some(CameraPathRequest::Clear) => {
+                player.path = CameraPath::default();
+                player.time = 0.0;
+                player.playing = false;
+            }
+            None => {}
+        }
+
+        let delta = time.delta_seconds();
+        if player.recording {
+            if !was_recording {
+                player.path = CameraPath::default();
+                player.time = 0.0;
+                player.playing = false;
+                self.since_sample = RECORD_INTERVAL;
+            }
+            if let Some(view) = current_view {
+                if self.since_sample >= RECORD_INTERVAL {
+                    let time = player.time;
+                    player.path.push(time, view);
+                    self.since_sample = 0.0;
+                }
+            }
+            player.time += delta;
+            self.since_sample += delta;
+            return;
+        }
+        if was_recording {
+            // Close the path with the view the recording stopped at.
+            if let Some(view) = current_view {
+                if player.time > player.path.duration() {
+                    let time = player.time;
+                    player.path.push(time, view);
+                }
+            }
+            player.time = 0.0;
+        }
+
+        let playing = player.playing;
+        if playing {
+            let duration = player.path.duration();
+            player.time += delta;
+            if player.time >= duration {
+                if player.looping && duration > 0.0 {
+                    player.time %= duration;
+                } else {
+                    player.time = duration;
+                    player.playing = false;
+                }
+            }
+        }
+        if playing || scrubbed {
+            if let (Some(view), Some(camera)) = (player.path.sample(player.time), camera) {
+                if let Some(transform) = transforms.get_mut(camera) {
+                    view.apply(transform, auto_fovs.get_mut(camera));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyframe(time: f32, x: f32) -> Keyframe {
+        Keyframe {
+            time,
+            view: CameraView {
+                position: [x, 0.0, 0.0],
+                rotation: [0.0, 0.0, 0.0, 1.0],
+                fov: 60.0,
+            },
+        }
+    }
+
+    fn path(keyframes: &[(f32, f32)]) -> CameraPath {
+        CameraPath {
+            keyframes: keyframes.iter().map(|&(time, x)| keyframe(time, x)).collect(),
+        }
+    }
+
+    fn x_at(path: &CameraPath, time: f32) -> f32 {
+        path.sample(time).unwrap().position[0]
+    }
+
+    fn times(path: &CameraPath) -> Vec<f32> {
+        path.keyframes.iter().map(|keyframe| keyframe.time).collect()
+    }
+
+    #[test]
+    fn catmull_rom_passes_through_the_inner_points() {
+        assert!((catmull_rom(0.0, 1.0, 4.0, 2.0, 0.0) - 1.0).abs() < 1.0e-6);
+        assert!((catmull_rom(0.0, 1.0, 4.0, 2.0, 1.0) - 4.0).abs() < 1.0e-6);
+        // Evenly spaced points on a line are interpolated linearly.
+        assert!((catmull_rom(0.0, 1.0, 2.0, 3.0, 0.25) - 1.25).abs() < 1.0e-6);
+    }
+
+    #[test]
+    fn sample_hits_the_keyframes() {
+        let path = path(&[(0.0, 0.0), (1.0, 3.0), (3.0, -2.0), (4.0, 5.0)]);
+        for keyframe in &path.keyframes {
+            assert!((x_at(&path, keyframe.time) - keyframe.view.position[0]).abs() < 1.0e-5);
+        }
+        let between = x_at(&path, 2.0);
+        assert!(between.is_finite() && between < 3.0 && between > -2.0);
+    }
+
+    #[test]
+    fn sample_holds_outside_of_the_path() {
+        let path = path(&[(1.0, 2.0), (2.0, 4.0)]);
+        assert!((x_at(&path, 0.0) - 2.0).abs() < 1.0e-6);
+        assert!((x_at(&path, 10.0) - 4.0).abs() < 1.0e-6);
+        assert!(CameraPath::default().sample(0.0).is_none());
+    }
+
+    #[test]
+    fn normalize_merges_duplicate_times() {
+        let mut path = path(&[(0.0, 0.0), (1.0, 1.0), (1.0, 7.0), (2.0, 2.0)]);
+        path.normalize();
+        assert_eq!(times(&path), vec![0.0, 1.0, 2.0]);
+        assert!((path.keyframes[1].view.position[0] - 1.0).abs() < 1.0e-6);
+        for step in 0..=20 {
+            assert!(x_at(&path, step as f32 * 0.1).is_finite());
+        }
+    }
+
+    #[test]
+    fn normalize_sorts_unsorted_input() {
+        let mut path = path(&[(2.0, 2.0), (0.0, 0.0), (1.0, 1.0)]);
+        path.normalize();
+        assert_eq!(times(&path), vec![0.0, 1.0, 2.0]);
+        // The segment ends at the last keyframe, which is repeated as the outer point.
+        assert!((x_at(&path, 1.5) - 1.5625).abs() < 1.0e-5);
+    }
+
+    #[test]
+    fn push_replaces_keyframes_at_or_after_the_time() {
+        let mut path = path(&[(0.0, 0.0), (1.0, 1.0), (2.0, 2.0)]);
+        path.push(1.0, keyframe(1.0, 9.0).view);
+        assert_eq!(times(&path), vec![0.0, 1.0]);
+        assert!((path.keyframes[1].view.position[0] - 9.0).abs() < 1.0e-6);
+    }
+}
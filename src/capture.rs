@@ -0,0 +1,106 @@
+//! Frame captures through RenderDoc, available with the `renderdoc` feature.
+use amethyst::{
+    core::{
+        ecs::{Read, System, Write},
+        shrev::{EventChannel, ReaderId},
+    },
+    input::InputEvent,
+    winit::VirtualKeyCode,
+};
+use amethyst_derive::SystemDesc;
+use derive_new::new;
+
+use crate::filtered_input::FilteredInputEvent;
+#[cfg(feature = "renderdoc")]
+use renderdoc::{RenderDoc, V120};
+
+/// Set to request a capture of the next frame.
+#[derive(Default, Debug)]
+pub struct CaptureRequest {
+    pub frame: bool,
+}
+
+/// Requests a capture when F9 is pressed outside of the UI.
+#[derive(Debug, SystemDesc, new)]
+#[system_desc(name(CaptureHotkeySystemDesc))]
+pub struct CaptureHotkeySystem {
+    #[system_desc(event_channel_reader)]
+    event_reader: ReaderId<FilteredInputEvent>,
+}
+
+impl<'a> System<'a> for CaptureHotkeySystem {
+    type SystemData = (Read<'a, EventChannel<FilteredInputEvent>>, Write<'a, CaptureRequest>);
+
+    fn run(&mut self, (events, mut request): Self::SystemData) {
+        for event in events.read(&mut self.event_reader) {
+            if let FilteredInputEvent::Free(InputEvent::KeyPressed {
+                key_code: VirtualKeyCode::F9,
+                ..
+            }) = *event
+            {
+                request.frame = true;
+            }
+        }
+    }
+}
+
+/// Outcome of the last capture related action, shown in the UI.
+#[derive(Default, Debug, Clone)]
+pub struct CaptureStatus {
+    pub available: bool,
+    pub message: String,
+}
+
+/// Owns the RenderDoc API handle.
+///
+/// The handle is not `Send`, so this lives in the state instead of the `World`.
+pub struct FrameCapture {
+    #[cfg(feature = "renderdoc")]
+    renderdoc: Option<RenderDoc<V120>>,
+}
+
+impl FrameCapture {
+    #[cfg(feature = "renderdoc")]
+    pub fn new(path_prefix: Option<&str>) -> (Self, CaptureStatus) {
+        match RenderDoc::<V120>::new() {
+            Ok(mut renderdoc) => {
+                if let Some(prefix) = path_prefix {
+                    renderdoc.set_capture_file_path_template(prefix);
+                }
+                let status = CaptureStatus {
+                    available: true,
+                    message: "RenderDoc ready.".into(),
+                };
+                (Self { renderdoc: Some(renderdoc) }, status)
+            }
+            Err(e) => {
+                let status = CaptureStatus {
+                    available: false,
+                    message: format!("RenderDoc not available: {}", e),
+                };
+                (Self { renderdoc: None }, status)
+            }
+        }
+    }
+
+    #[cfg(not(feature = "renderdoc"))]
+    pub fn new(_path_prefix: Option<&str>) -> (Self, CaptureStatus) {
+        let status = CaptureStatus {
+            available: false,
+            message: "RenderDoc not available: built without the `renderdoc` feature.".into(),
+        };
+        (Self {}, status)
+    }
+
+    /// Captures the next frame presented to the window.
+    #[cfg(feature = "renderdoc")]
+    pub fn trigger(&mut self, status: &mut CaptureStatus) {
+        if let Some(ref mut renderdoc) = self.renderdoc {
+            renderdoc.trigger_capture();
+            status.message = format!("Capture {} triggered.", renderdoc.get_num_captures() + 1);
+        }
+    }
+
+    #[cfg(not(feature = "renderdoc"))]
+    pub fn trigger(&mut self, _status: &mut CaptureStatus) {}
+}
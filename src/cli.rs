@@ -0,0 +1,152 @@
+use amethyst::Error;
+
+use std::path::PathBuf;
+
+use crate::{
+    benchmark::{DEFAULT_FRAMES, DEFAULT_WARMUP},
+    scenes::SceneInfo,
+    screenshot,
+    RenderMode,
+};
+
+/// Settings passed on the command line, applied before the application starts.
+#[derive(Clone, Debug, Default)]
+pub struct LaunchOptions {
+    pub scene: Option<String>,
+    pub mode: Option<RenderMode>,
+    pub size: Option<(u32, u32)>,
+    pub free_camera: bool,
+    pub capture_prefix: Option<String>,
+    /// Steps per second for deterministic fixed stepping.
+    pub fixed_step: Option<f32>,
+    /// Report file of a benchmark run; the application exits when it is written.
+    pub benchmark: Option<PathBuf>,
+    pub warmup: Option<u32>,
+    pub frames: Option<u32>,
+    pub screenshot_dir: Option<PathBuf>,
+    pub help: bool,
+}
+
+impl LaunchOptions {
+    /// Parses the arguments following the program name.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self, Error> {
+        let mut options = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => options.help = true,
+                "--free-camera" => options.free_camera = true,
+                "--scene" => options.scene = Some(value(&arg, args.next())?),
+                "--mode" => {
+                    let mode = value(&arg, args.next())?;
+                    options.mode = Some(RenderMode::from_name(&mode).ok_or_else(|| {
+                        Error::from_string(format!("Unknown render mode `{}`", mode))
+                    })?);
+                }
+                "--size" => options.size = Some(parse_size(&value(&arg, args.next())?)?),
+                "--capture-prefix" if cfg!(feature = "renderdoc") => {
+                    options.capture_prefix = Some(value(&arg, args.next())?)
+                }
+                "--capture-prefix" => {
+                    return Err(Error::from_string(
+                        "`--capture-prefix` needs a build with the `renderdoc` feature",
+                    ))
+                }
+                "--fixed-step" => {
+                    let rate = value(&arg, args.next())?;
+                    options.fixed_step = Some(
+                        rate.parse()
+                            .ok()
+                            .filter(|rate: &f32| *rate > 0.0)
+                            .ok_or_else(|| Error::from_string(format!("Invalid step rate `{}`", rate)))?,
+                    );
+                }
+                "--benchmark" => options.benchmark = Some(PathBuf::from(value(&arg, args.next())?)),
+                "--warmup" => options.warmup = Some(parse_count(&arg, &value(&arg, args.next())?)?),
+                "--frames" => options.frames = Some(parse_count(&arg, &value(&arg, args.next())?)?),
+                "--screenshot-dir" => options.screenshot_dir = Some(PathBuf::from(value(&arg, args.next())?)),
+                _ => return Err(Error::from_string(format!("Unknown argument `{}`, see --help", arg))),
+            }
+        }
+        Ok(options)
+    }
+
+    /// Resolves `--scene` to an index into `scenes`, matching either the scene key or its display name.
+    pub fn scene_index(&self, scenes: &[SceneInfo]) -> Result<usize, Error> {
+        let name = match self.scene {
+            Some(ref name) => name,
+            None => return Ok(0),
+        };
+        scenes
+            .iter()
+            .position(|s| s.name.eq_ignore_ascii_case(name) || s.display_name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| Error::from_string(format!("Unknown scene `{}`, see --help", name)))
+    }
+}
+
+fn value(arg: &str, value: Option<String>) -> Result<String, Error> {
+    value.ok_or_else(|| Error::from_string(format!("Missing value for `{}`", arg)))
+}
+
+fn parse_count(arg: &str, count: &str) -> Result<u32, Error> {
+    count
+        .parse()
+        .ok()
+        .filter(|count| *count > 0)
+        .ok_or_else(|| Error::from_string(format!("Invalid frame count `{}` for `{}`", count, arg)))
+}
+
+fn parse_size(size: &str) -> Result<(u32, u32), Error> {
+    let mut parts = size.splitn(2, 'x');
+    match (parts.next().map(str::parse), parts.next().map(str::parse)) {
+        (Some(Ok(width)), Some(Ok(height))) if width > 0 && height > 0 => Ok((width, height)),
+        _ => Err(Error::from_string(format!("Invalid size `{}`, expected WIDTHxHEIGHT", size))),
+    }
+}
+
+pub fn print_help(scenes: &[SceneInfo]) {
+    println!("Usage: amethst-lightroom [OPTIONS]");
+    println!();
+    println!("Options:");
+    println!("    --scene <NAME>        Scene to load at startup");
+    println!("    --mode <MODE>         Render mode to start in");
+    println!("    --size <WxH>          Window size, e.g. 1920x1080");
+    println!("    --free-camera         Enable free camera movement");
+    if cfg!(feature = "renderdoc") {
+        println!("    --capture-prefix <P>  Path prefix for RenderDoc captures (F9)");
+    }
+    println!("    --fixed-step <HZ>     Advance every frame by 1/HZ seconds instead of the real frame time");
+    println!("    --benchmark <FILE>    Time every scene in every mode, write a CSV or JSON report and exit");
+    println!("    --warmup <N>          Benchmark frames rendered before measuring (default {})", DEFAULT_WARMUP);
+    println!("    --frames <N>          Benchmark frames measured per scene and mode (default {})", DEFAULT_FRAMES);
+    println!("    --screenshot-dir <D>  Directory for screenshots (F12), default `{}`", screenshot::DEFAULT_DIR);
+    println!("    -h, --help            Print this help");
+    println!();
+    println!("Scenes:");
+    for scene in scenes {
+        println!("    {:<20}  {}", scene.name, scene.display_name);
+    }
+    println!();
+    println!("Modes:");
+    for mode in RenderMode::ALL.iter() {
+        println!("    {}", mode.name());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_size_accepts_width_and_height() {
+        assert_eq!(parse_size("1920x1080").unwrap(), (1920, 1080));
+        assert_eq!(parse_size("1x1").unwrap(), (1, 1));
+    }
+
+    #[test]
+    fn parse_size_rejects_zero_and_malformed_sizes() {
+        for size in &["0x1080", "1920x0", "0x0", "1920", "1920x", "x1080", "-1x5", "axb", "1920x1080x2"] {
+            assert!(parse_size(size).is_err(), "`{}` was accepted", size);
+        }
+    }
+}
@@ -0,0 +1,116 @@
+//! Deterministic frame stepping for benchmarks and image comparison.
+use amethyst::{assets::HotReloadStrategy, core::Time};
+
+/// Steps per second used when fixed stepping is enabled from the UI.
+pub const DEFAULT_RATE: f32 = 60.0;
+
+/// While enabled, every frame advances the simulation by `delta` instead of the wall-clock frame
+/// time, so frame N shows the same image on every run.
+///
+/// Asset hot reloading is paused while enabled, since it polls the file system on a wall-clock timer.
+///
+/// Only the deltas of `Time` are fixed. The core loop has already added the wall-clock delta to
+/// `Time::absolute_time` when `advance` runs, so it is not deterministic; use `elapsed` instead.
+#[derive(Clone, Debug)]
+pub struct FixedStep {
+    pub enabled: bool,
+    /// Seconds per frame.
+    pub delta: f32,
+    /// Frames stepped since fixed stepping was enabled.
+    pub frame: u64,
+    /// Whether the last `advance` ran with fixed stepping.
+    active: bool,
+}
+
+impl Default for FixedStep {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            delta: 1.0 / DEFAULT_RATE,
+            frame: 0,
+            active: false,
+        }
+    }
+}
+
+impl FixedStep {
+    pub fn new(rate: Option<f32>) -> Self {
+        Self {
+            enabled: rate.is_some(),
+            delta: 1.0 / rate.unwrap_or(DEFAULT_RATE),
+            ..Default::default()
+        }
+    }
+
+    /// Simulation time since fixed stepping was enabled.
+    pub fn elapsed(&self) -> f32 {
+        self.frame as f32 * self.delta
+    }
+
+    /// Replaces the frame delta of `time`; called once per frame before the systems run.
+    ///
+    /// `Time::absolute_time` keeps the wall-clock time and advances by `delta` on top of it.
+    pub fn advance(&mut self, time: &mut Time, hot_reload: &mut HotReloadStrategy) {
+        if self.enabled != self.active {
+            self.active = self.enabled;
+            self.frame = 0;
+            *hot_reload = if self.enabled {
+                HotReloadStrategy::never()
+            } else {
+                HotReloadStrategy::default()
+            };
+        }
+        if self.enabled {
+            time.set_delta_seconds(self.delta);
+            self.frame += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn advance_replaces_the_real_delta() {
+        let mut fixed_step = FixedStep::new(Some(50.0));
+        let mut time = Time::default();
+        let mut hot_reload = HotReloadStrategy::default();
+        for _ in 0..3 {
+            time.set_delta_seconds(0.5);
+            fixed_step.advance(&mut time, &mut hot_reload);
+            assert!((time.delta_seconds() - 0.02).abs() < 1.0e-6);
+        }
+        assert_eq!(fixed_step.frame, 3);
+        assert!((fixed_step.elapsed() - 0.06).abs() < 1.0e-6);
+    }
+
+    #[test]
+    fn disabling_restarts_the_frame_count() {
+        let mut fixed_step = FixedStep::new(Some(60.0));
+        let mut time = Time::default();
+        let mut hot_reload = HotReloadStrategy::default();
+        fixed_step.advance(&mut time, &mut hot_reload);
+        fixed_step.enabled = false;
+        time.set_delta_seconds(0.5);
+        fixed_step.advance(&mut time, &mut hot_reload);
+        assert_eq!(fixed_step.frame, 0);
+        assert!((time.delta_seconds() - 0.5).abs() < 1.0e-6);
+    }
+
+    /// `Time::absolute_time` mixes wall-clock and fixed deltas, see `FixedStep`.
+    #[test]
+    fn absolute_time_is_not_used() {
+        let src = concat!(env!("CARGO_MANIFEST_DIR"), "/src");
+        for entry in fs::read_dir(src).unwrap() {
+            let path = entry.unwrap().path();
+            if path.file_name().map_or(false, |name| name == "fixed_step.rs") {
+                continue;
+            }
+            let source = fs::read_to_string(&path).unwrap();
+            assert!(!source.contains("absolute_time"), "{} uses Time::absolute_time", path.display());
+            assert!(!source.contains("absolute_real_time"), "{} uses Time::absolute_real_time", path.display());
+        }
+    }
+}
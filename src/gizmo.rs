@@ -0,0 +1,381 @@
+//! In-viewport manipulator to translate, rotate and scale the selected entity.
+use amethyst::{
+    core::{
+        ecs::{Read, ReadExpect, ReadStorage, System, SystemData, World, Write, WriteStorage},
+        math::{Matrix4, Point3, Unit, UnitQuaternion, Vector3},
+        shrev::{EventChannel, ReaderId},
+        SystemDesc, Transform,
+    },
+    input::{InputEvent, InputHandler, StringBindings},
+    renderer::{debug_drawing::DebugLines, palette::Srgba, ActiveCamera, Camera},
+    window::ScreenDimensions,
+    winit::MouseButton,
+};
+
+use crate::{
+    filtered_input::FilteredInputEvent,
+    history::{Edit, History},
+    light_debug, picking,
+    ui::UIState,
+};
+
+pub const TRANSLATE: usize = 0;
+pub const ROTATE: usize = 1;
+pub const SCALE: usize = 2;
+
+pub const GIZMO_MODES: [&str; 3] = ["Translate", "Rotate", "Scale"];
+
+/// Size of the handles relative to their distance from the camera, so they keep their size on screen.
+const SCREEN_SIZE: f32 = 0.15;
+/// Distance from a handle, relative to its size, up to which the cursor still grabs it.
+const GRAB_TOLERANCE: f32 = 0.08;
+/// Smallest scale a handle can shrink an axis to.
+const MIN_SCALE: f32 = 0.01;
+
+/// Settings of the transform gizmo, edited from the UI.
+#[derive(Clone, Debug)]
+pub struct Gizmo {
+    pub mode: usize,
+    /// Align translate and rotate handles with the entity instead of the world axes.
+    /// Scale handles always follow the entity.
+    pub local_space: bool,
+    pub snap: bool,
+    /// Grid size in meters.
+    pub snap_translation: f32,
+    /// Angle increment in degrees.
+    pub snap_angle: f32,
+    pub snap_scale: f32,
+    /// Set while a handle is dragged, the camera and picking leave the mouse alone meanwhile.
+    pub dragging: bool,
+}
+
+impl Default for Gizmo {
+    fn default() -> Self {
+        Self {
+            mode: TRANSLATE,
+            local_space: false,
+            snap: false,
+            snap_translation: 0.25,
+            snap_angle: 15.0,
+            snap_scale: 0.1,
+            dragging: false,
+        }
+    }
+}
+
+/// State captured when a handle is grabbed; drags are applied relative to it.
+#[derive(Clone, Debug)]
+struct Drag {
+    axis: usize,
+    mode: usize,
+    /// World space direction of the grabbed axis.
+    direction: Vector3<f32>,
+    origin: Point3<f32>,
+    /// Where the cursor ray first met the handle, along the axis or in the plane of the ring.
+    grab: Vector3<f32>,
+    local: Transform,
+    /// Maps world space into the space of the entity's parent.
+    parent_inverse: Matrix4<f32>,
+}
+
+/// Draws axis arrows, rotation rings or scale handles around `UIState::selected` and drags them
+/// with the left mouse button. Each drag is recorded as one `History` command.
+pub struct GizmoSystem {
+    event_reader: ReaderId<FilteredInputEvent>,
+    drag: Option<Drag>,
+}
+
+impl<'a> System<'a> for GizmoSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Read<'a, EventChannel<FilteredInputEvent>>,
+        Read<'a, InputHandler<StringBindings>>,
+        ReadExpect<'a, ScreenDimensions>,
+        Read<'a, ActiveCamera>,
+        ReadStorage<'a, Camera>,
+        Read<'a, UIState>,
+        Write<'a, Gizmo>,
+        Write<'a, History>,
+        WriteStorage<'a, Transform>,
+        Write<'a, DebugLines>,
+    );
+
+    fn run(
+        &mut self,
+        (events, input, screen, active_camera, cameras, state, mut gizmo, mut history, mut transforms, mut debug_lines): Self::SystemData,
+    ) {
+        let mut pressed = false;
+        let mut released = false;
+        for event in events.read(&mut self.event_reader) {
+            match *event {
+                FilteredInputEvent::Free(InputEvent::MouseButtonPressed(MouseButton::Left)) => pressed = true,
+                // Releases over imgui still have to end the drag.
+                FilteredInputEvent::Free(InputEvent::MouseButtonReleased(MouseButton::Left))
+                | FilteredInputEvent::Filtered(InputEvent::MouseButtonReleased(MouseButton::Left)) => released = true,
+                _ => {}
+            }
+        }
+
+        let selected = match state.selected.filter(|entity| transforms.contains(*entity)) {
+            Some(selected) => selected,
+            None => {
+                self.drag = None;
+                gizmo.dragging = false;
+                return;
+            }
+        };
+        let camera = active_camera
+            .entity
+            .and_then(|entity| Some((cameras.get(entity)?, transforms.get(entity)?)));
+        let ray = match (camera, input.mouse_position()) {
+            (Some((camera, camera_transform)), Some(position)) => {
+                Some(picking::cursor_ray(camera, camera_transform, &screen, position))
+            }
+            _ => None,
+        };
+        let camera_position = camera.map(|(_, transform)| transform.global_matrix().transform_point(&Point3::origin()));
+
+        let (global, local) = {
+            let transform = transforms.get(selected).unwrap();
+            (*transform.global_matrix(), transform.clone())
+        };
+        let origin = global.transform_point(&Point3::origin());
+        let size = camera_position.map_or(1.0, |camera| (camera - origin).norm() * SCREEN_SIZE);
+        let mode = gizmo.mode;
+        let axes = handle_axes(&global, mode == SCALE || gizmo.local_space);
+
+        let hovered = match (&self.drag, ray) {
+            (None, Some((ray_origin, ray_direction))) => {
+                hit_handle(mode, &origin, &axes, size, &ray_origin, &ray_direction)
+            }
+            _ => None,
+        };
+
+        if pressed && self.drag.is_none() {
+            if let (Some(axis), Some((ray_origin, ray_direction))) = (hovered, ray) {
+                let direction = axes[axis];
+                let grab = match mode {
+                    ROTATE => intersect_plane(&ray_origin, &ray_direction, &origin, &direction).map(|hit| hit - origin),
+                    _ => closest_on_axis(&ray_origin, &ray_direction, &origin, &direction).map(|s| direction * s),
+                };
+                if let Some(grab) = grab {
+                    self.drag = Some(Drag {
+                        axis,
+                        mode,
+                        direction,
+                        origin,
+                        grab,
+                        parent_inverse: local.matrix() * global.try_inverse().unwrap_or_else(Matrix4::identity),
+                        local,
+                    });
+                }
+            }
+        }
+
+        if let (Some(drag), Some((ray_origin, ray_direction))) = (&self.drag, ray) {
+            if let Some(transform) = transforms.get_mut(selected) {
+                drag.apply(&gizmo, &ray_origin, &ray_direction, transform);
+                if transform.matrix() != drag.local.matrix() {
+                    history.record(
+                        format!("{} entity {}", GIZMO_MODES[drag.mode], selected.id()),
+                        vec![Edit::transform(selected, drag.local.clone(), transform.clone())],
+                    );
+                }
+            }
+        }
+        if released {
+            self.drag = None;
+        }
+        gizmo.dragging = self.drag.is_some();
+
+        let active = self.drag.as_ref().map(|drag| drag.axis).or(hovered);
+        for (axis, direction) in axes.iter().enumerate() {
+            let color = if active == Some(axis) {
+                Srgba::new(1.0, 0.9, 0.2, 1.0)
+            } else {
+                let mut color = [0.2, 0.2, 0.2];
+                color[axis] = 1.0;
+                Srgba::new(color[0], color[1], color[2], 1.0)
+            };
+            let end = origin + direction * size;
+            match mode {
+                TRANSLATE => light_debug::draw_world_arrow(&mut debug_lines, origin, end, color),
+                ROTATE => light_debug::draw_world_circle(&mut debug_lines, origin, *direction, size, color),
+                _ => {
+                    debug_lines.draw_line(origin, end, color);
+                    light_debug::draw_wire_sphere(&mut debug_lines, end, size * GRAB_TOLERANCE, color);
+                }
+            }
+        }
+    }
+}
+
+impl Drag {
+    /// Moves `transform` to follow the cursor ray, starting from the state at the time of the grab.
+    fn apply(&self, gizmo: &Gizmo, ray_origin: &Point3<f32>, ray_direction: &Vector3<f32>, transform: &mut Transform) {
+        match self.mode {
+            TRANSLATE => {
+                let along = match closest_on_axis(ray_origin, ray_direction, &self.origin, &self.direction) {
+                    Some(along) => along,
+                    None => return,
+                };
+                let mut delta = along - self.grab.dot(&self.direction);
+                if gizmo.snap && gizmo.snap_translation > 0.0 {
+                    if gizmo.local_space {
+                        delta = snap(delta, gizmo.snap_translation);
+                    } else {
+                        // World axes snap the coordinate itself onto the grid.
+                        let start = self.origin.coords.dot(&self.direction);
+                        delta = snap(start + delta, gizmo.snap_translation) - start;
+                    }
+                }
+                let world = self.origin + self.direction * delta;
+                transform.set_translation(self.parent_inverse.transform_point(&world).coords);
+            }
+            ROTATE => {
+                let hit = match intersect_plane(ray_origin, ray_direction, &self.origin, &self.direction) {
+                    Some(hit) => hit - self.origin,
+                    None => return,
+                };
+                let mut angle = self.direction.dot(&self.grab.cross(&hit)).atan2(self.grab.dot(&hit));
+                if gizmo.snap && gizmo.snap_angle > 0.0 {
+                    angle = snap(angle.to_degrees(), gizmo.snap_angle).to_radians();
+                }
+                let axis = self.parent_inverse.transform_vector(&self.direction);
+                if axis.norm_squared() < std::f32::EPSILON {
+                    return;
+                }
+                let delta = UnitQuaternion::from_axis_angle(&Unit::new_normalize(axis), angle);
+                transform.set_rotation(delta * self.local.rotation());
+            }
+            _ => {
+                let along = match closest_on_axis(ray_origin, ray_direction, &self.origin, &self.direction) {
+                    Some(along) => along,
+                    None => return,
+                };
+                let grab = self.grab.dot(&self.direction);
+                if grab.abs() < std::f32::EPSILON {
+                    return;
+                }
+                let mut scale = *self.local.scale();
+                scale[self.axis] *= along / grab;
+                if gizmo.snap && gizmo.snap_scale > 0.0 {
+                    scale[self.axis] = snap(scale[self.axis], gizmo.snap_scale);
+                }
+                scale[self.axis] = scale[self.axis].max(MIN_SCALE);
+                transform.set_scale(scale);
+            }
+        }
+    }
+}
+
+fn snap(value: f32, step: f32) -> f32 {
+    (value / step).round() * step
+}
+
+/// World space directions of the X, Y and Z handles.
+fn handle_axes(global: &Matrix4<f32>, local_space: bool) -> [Vector3<f32>; 3] {
+    let mut axes = [Vector3::x(), Vector3::y(), Vector3::z()];
+    if local_space {
+        for axis in axes.iter_mut() {
+            let direction = global.transform_vector(axis);
+            if direction.norm_squared() > std::f32::EPSILON {
+                *axis = direction.normalize();
+            }
+        }
+    }
+    axes
+}
+
+/// Index of the nearest handle under the cursor ray.
+fn hit_handle(
+    mode: usize,
+    origin: &Point3<f32>,
+    axes: &[Vector3<f32>; 3],
+    size: f32,
+    ray_origin: &Point3<f32>,
+    ray_direction: &Vector3<f32>,
+) -> Option<usize> {
+    let tolerance = size * GRAB_TOLERANCE;
+    let mut nearest: Option<(f32, usize)> = None;
+    for (axis, direction) in axes.iter().enumerate() {
+        let hit = if mode == ROTATE {
+            intersect_plane(ray_origin, ray_direction, origin, direction).and_then(|hit| {
+                let off_ring = ((hit - origin).norm() - size).abs();
+                if off_ring <= tolerance {
+                    Some((hit - ray_origin).norm())
+                } else {
+                    None
+                }
+            })
+        } else {
+            closest_on_axis(ray_origin, ray_direction, origin, direction).and_then(|along| {
+                let on_axis = origin + direction * along.max(0.0).min(size);
+                let to_ray = on_axis - ray_origin;
+                let distance = (to_ray - ray_direction * to_ray.dot(ray_direction)).norm();
+                if along >= 0.0 && along <= size + tolerance && distance <= tolerance {
+                    Some(to_ray.dot(ray_direction))
+                } else {
+                    None
+                }
+            })
+        };
+        if let Some(distance) = hit {
+            if nearest.map_or(true, |(nearest, _)| distance < nearest) {
+                nearest = Some((distance, axis));
+            }
+        }
+    }
+    nearest.map(|(_, axis)| axis)
+}
+
+/// Parameter along the normalized axis `direction` of the point closest to the ray, `None` if they are parallel.
+fn closest_on_axis(
+    ray_origin: &Point3<f32>,
+    ray_direction: &Vector3<f32>,
+    origin: &Point3<f32>,
+    direction: &Vector3<f32>,
+) -> Option<f32> {
+    let offset = ray_origin - origin;
+    let b = ray_direction.dot(direction);
+    let denominator = 1.0 - b * b;
+    if denominator < 1e-6 {
+        return None;
+    }
+    let d = ray_direction.dot(&offset);
+    let e = direction.dot(&offset);
+    Some((e - b * d) / denominator)
+}
+
+/// Point where the ray crosses the plane through `origin` orthogonal to `normal`.
+fn intersect_plane(
+    ray_origin: &Point3<f32>,
+    ray_direction: &Vector3<f32>,
+    origin: &Point3<f32>,
+    normal: &Vector3<f32>,
+) -> Option<Point3<f32>> {
+    let facing = ray_direction.dot(normal);
+    if facing.abs() < 1e-4 {
+        return None;
+    }
+    let distance = (origin - ray_origin).dot(normal) / facing;
+    if distance < 0.0 {
+        return None;
+    }
+    Some(ray_origin + ray_direction * distance)
+}
+
+#[derive(Default)]
+pub struct GizmoSystemDesc;
+impl<'a, 'b> SystemDesc<'a, 'b, GizmoSystem> for GizmoSystemDesc {
+    fn build(self, world: &mut World) -> GizmoSystem {
+        <GizmoSystem as System<'_>>::SystemData::setup(world);
+
+        let event_reader = Write::<EventChannel<FilteredInputEvent>>::fetch(world).register_reader();
+
+        GizmoSystem {
+            event_reader,
+            drag: None,
+        }
+    }
+}
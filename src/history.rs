@@ -0,0 +1,511 @@
+//! Undo/redo history of the edits made to the scene.
+use amethyst::{
+    assets::Handle,
+    core::{
+        ecs::{Entities, Entity, Read, System, SystemData, World, Write, WriteStorage},
+        shrev::{EventChannel, ReaderId},
+        HiddenPropagate, Named, Parent, SystemDesc, Transform,
+    },
+    input::{InputEvent, InputHandler, StringBindings},
+    renderer::{light::Light, resources::Tint, Camera, Material, Transparent},
+    utils::auto_fov::AutoFov,
+    winit::{MouseButton, VirtualKeyCode},
+};
+
+use crate::{
+    filtered_input::FilteredInputEvent,
+    materials::{Factors, MaterialFactors},
+    prefab_data::{PrefabIndex, Scene},
+};
+
+/// Number of commands kept on the undo stack.
+const HISTORY_LIMIT: usize = 100;
+
+/// Everything needed to bring back an entity created or deleted by an edit.
+#[derive(Clone, Debug)]
+pub struct EntitySnapshot {
+    pub transform: Transform,
+    pub light: Option<Light>,
+    pub name: Option<String>,
+    pub parent: Option<Entity>,
+    pub prefab_index: Option<PrefabIndex>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Change {
+    Transform { before: Transform, after: Transform },
+    Light { before: Light, after: Light },
+    Parent { before: Option<Entity>, after: Option<Entity> },
+    Name { before: Option<String>, after: Option<String> },
+    Hidden { before: bool, after: bool },
+    Camera {
+        before: (Camera, Option<AutoFov>),
+        after: (Camera, Option<AutoFov>),
+    },
+    Tint { before: Option<Tint>, after: Option<Tint> },
+    Transparent { before: bool, after: bool },
+    /// The material of the entity and the factors it was edited with.
+    Material {
+        before: (Handle<Material>, Option<Factors>),
+        after: (Handle<Material>, Option<Factors>),
+    },
+    Create(EntitySnapshot),
+    Delete(EntitySnapshot),
+}
+
+impl Change {
+    fn same_kind(&self, other: &Change) -> bool {
+        match (self, other) {
+            (Change::Transform { .. }, Change::Transform { .. })
+            | (Change::Light { .. }, Change::Light { .. })
+            | (Change::Camera { .. }, Change::Camera { .. })
+            | (Change::Tint { .. }, Change::Tint { .. })
+            | (Change::Material { .. }, Change::Material { .. }) => true,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Edit {
+    pub entity: Entity,
+    pub change: Change,
+}
+
+impl Edit {
+    pub fn transform(entity: Entity, before: Transform, after: Transform) -> Self {
+        Self {
+            entity,
+            change: Change::Transform { before, after },
+        }
+    }
+
+    pub fn light(entity: Entity, before: Light, after: Light) -> Self {
+        Self {
+            entity,
+            change: Change::Light { before, after },
+        }
+    }
+
+    pub fn parent(entity: Entity, before: Option<Entity>, after: Option<Entity>) -> Self {
+        Self {
+            entity,
+            change: Change::Parent { before, after },
+        }
+    }
+
+    pub fn name(entity: Entity, before: Option<String>, after: Option<String>) -> Self {
+        Self {
+            entity,
+            change: Change::Name { before, after },
+        }
+    }
+
+    pub fn hidden(entity: Entity, before: bool, after: bool) -> Self {
+        Self {
+            entity,
+            change: Change::Hidden { before, after },
+        }
+    }
+
+    pub fn camera(entity: Entity, before: (Camera, Option<AutoFov>), after: (Camera, Option<AutoFov>)) -> Self {
+        Self {
+            entity,
+            change: Change::Camera { before, after },
+        }
+    }
+
+    pub fn tint(entity: Entity, before: Option<Tint>, after: Option<Tint>) -> Self {
+        Self {
+            entity,
+            change: Change::Tint { before, after },
+        }
+    }
+
+    pub fn transparent(entity: Entity, before: bool, after: bool) -> Self {
+        Self {
+            entity,
+            change: Change::Transparent { before, after },
+        }
+    }
+
+    pub fn material(
+        entity: Entity,
+        before: (Handle<Material>, Option<Factors>),
+        after: (Handle<Material>, Option<Factors>),
+    ) -> Self {
+        Self {
+            entity,
+            change: Change::Material { before, after },
+        }
+    }
+}
+
+/// A labelled group of edits that is undone and redone as a whole.
+#[derive(Clone, Debug)]
+pub struct Command {
+    pub label: String,
+    edits: Vec<Edit>,
+}
+
+impl Command {
+    /// Whether `edits` continue this command, e.g. the next frame of a slider or gizmo drag.
+    fn continues(&self, label: &str, edits: &[Edit]) -> bool {
+        self.label == label
+            && self.edits.len() == edits.len()
+            && self
+                .edits
+                .iter()
+                .zip(edits)
+                .all(|(a, b)| a.entity == b.entity && a.change.same_kind(&b.change))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HistoryStep {
+    Undo,
+    Redo,
+}
+
+/// Commands recorded by the editing systems, applied in reverse by `HistorySystem`.
+#[derive(Default, Debug)]
+pub struct History {
+    undo: Vec<Command>,
+    redo: Vec<Command>,
+    /// Whether the newest command absorbs further edits with the same label.
+    /// Closed whenever the left mouse button is released, so one drag becomes one command.
+    open: bool,
+    pub request: Option<HistoryStep>,
+}
+
+impl History {
+    pub fn record(&mut self, label: String, edits: Vec<Edit>) {
+        if edits.is_empty() {
+            return;
+        }
+        self.redo.clear();
+        if self.open {
+            if let Some(top) = self.undo.last_mut() {
+                if top.continues(&label, &edits) {
+                    for (top, edit) in top.edits.iter_mut().zip(edits) {
+                        match (&mut top.change, edit.change) {
+                            (Change::Transform { ref mut after, .. }, Change::Transform { after: latest, .. }) => {
+                                *after = latest
+                            }
+                            (Change::Light { ref mut after, .. }, Change::Light { after: latest, .. }) => *after = latest,
+                            (Change::Camera { ref mut after, .. }, Change::Camera { after: latest, .. }) => *after = latest,
+                            (Change::Tint { ref mut after, .. }, Change::Tint { after: latest, .. }) => *after = latest,
+                            (Change::Material { ref mut after, .. }, Change::Material { after: latest, .. }) => {
+                                *after = latest
+                            }
+                            _ => {}
+                        }
+                    }
+                    return;
+                }
+            }
+        }
+        self.undo.push(Command { label, edits });
+        if self.undo.len() > HISTORY_LIMIT {
+            self.undo.remove(0);
+        }
+        self.open = true;
+    }
+
+    /// Starts a new command with the next edit.
+    pub fn seal(&mut self) {
+        self.open = false;
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.open = false;
+    }
+
+    /// Commands that can be undone, oldest first.
+    pub fn undo_stack(&self) -> &[Command] {
+        &self.undo
+    }
+
+    /// Commands that can be redone, the next one last.
+    pub fn redo_stack(&self) -> &[Command] {
+        &self.redo
+    }
+
+    /// Points all commands at `new` after an entity was recreated.
+    fn remap(&mut self, old: Entity, new: Entity) {
+        for command in self.undo.iter_mut().chain(self.redo.iter_mut()) {
+            for edit in command.edits.iter_mut() {
+                if edit.entity == old {
+                    edit.entity = new;
+                }
+                let parents = match edit.change {
+                    Change::Create(ref mut snapshot) | Change::Delete(ref mut snapshot) => vec![&mut snapshot.parent],
+                    Change::Parent {
+                        ref mut before,
+                        ref mut after,
+                    } => vec![before, after],
+                    _ => continue,
+                };
+                for parent in parents {
+                    if *parent == Some(old) {
+                        *parent = Some(new);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Applies undo/redo requests from the UI and Ctrl+Z / Ctrl+Y (Ctrl+Shift+Z).
+///
+/// Key presses imgui takes for its text fields are filtered out, so those keep their own undo.
+/// The history is cleared when another scene is loaded, as its entities are gone.
+pub struct HistorySystem {
+    event_reader: ReaderId<FilteredInputEvent>,
+    scene: Option<usize>,
+}
+
+impl<'a> System<'a> for HistorySystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Read<'a, EventChannel<FilteredInputEvent>>,
+        Read<'a, InputHandler<StringBindings>>,
+        Read<'a, Scene>,
+        Write<'a, History>,
+        Entities<'a>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, Light>,
+        WriteStorage<'a, Parent>,
+        WriteStorage<'a, PrefabIndex>,
+        WriteStorage<'a, Named>,
+        WriteStorage<'a, HiddenPropagate>,
+        WriteStorage<'a, Camera>,
+        WriteStorage<'a, AutoFov>,
+        WriteStorage<'a, Tint>,
+        WriteStorage<'a, Transparent>,
+        WriteStorage<'a, Handle<Material>>,
+        Write<'a, MaterialFactors>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            events,
+            input,
+            scene,
+            mut history,
+            entities,
+            mut transforms,
+            mut lights,
+            mut parents,
+            mut indices,
+            mut names,
+            mut hidden,
+            mut cameras,
+            mut auto_fovs,
+            mut tints,
+            mut transparents,
+            mut materials,
+            mut factors,
+        ): Self::SystemData,
+    ) {
+        if scene.scene != self.scene {
+            self.scene = scene.scene;
+            history.clear();
+        }
+
+        let ctrl = input.key_is_down(VirtualKeyCode::LControl) || input.key_is_down(VirtualKeyCode::RControl);
+        let shift = input.key_is_down(VirtualKeyCode::LShift) || input.key_is_down(VirtualKeyCode::RShift);
+        for event in events.read(&mut self.event_reader) {
+            match *event {
+                FilteredInputEvent::Free(InputEvent::KeyPressed { key_code, .. }) if ctrl => match key_code {
+                    VirtualKeyCode::Z if shift => history.request = Some(HistoryStep::Redo),
+                    VirtualKeyCode::Z => history.request = Some(HistoryStep::Undo),
+                    VirtualKeyCode::Y => history.request = Some(HistoryStep::Redo),
+                    _ => {}
+                },
+                FilteredInputEvent::Free(InputEvent::MouseButtonReleased(MouseButton::Left))
+                | FilteredInputEvent::Filtered(InputEvent::MouseButtonReleased(MouseButton::Left)) => history.seal(),
+                _ => {}
+            }
+        }
+
+        let step = match history.request.take() {
+            Some(step) => step,
+            None => return,
+        };
+        history.seal();
+        let command = match step {
+            HistoryStep::Undo => history.undo.pop(),
+            HistoryStep::Redo => history.redo.pop(),
+        };
+        let command = match command {
+            Some(command) => command,
+            None => return,
+        };
+
+        let undo = step == HistoryStep::Undo;
+        let mut remaps = Vec::new();
+        let mut apply = |edit: &Edit| {
+            let entity = remaps
+                .iter()
+                .find(|(old, _)| *old == edit.entity)
+                .map_or(edit.entity, |(_, new)| *new);
+            match edit.change {
+                Change::Transform { ref before, ref after } => {
+                    let transform = if undo { before } else { after };
+                    if entities.is_alive(entity) {
+                        let _ = transforms.insert(entity, transform.clone());
+                    }
+                }
+                Change::Light { ref before, ref after } => {
+                    let light = if undo { before } else { after };
+                    if entities.is_alive(entity) {
+                        let _ = lights.insert(entity, light.clone());
+                    }
+                }
+                Change::Parent { before, after } => {
+                    let parent = if undo { before } else { after };
+                    let parent = parent.map(|parent| {
+                        remaps
+                            .iter()
+                            .find(|(old, _)| *old == parent)
+                            .map_or(parent, |(_, new)| *new)
+                    });
+                    if entities.is_alive(entity) {
+                        match parent.filter(|parent| entities.is_alive(*parent)) {
+                            Some(parent) => {
+                                let _ = parents.insert(entity, Parent { entity: parent });
+                            }
+                            None => {
+                                parents.remove(entity);
+                            }
+                        }
+                    }
+                }
+                Change::Name { ref before, ref after } => {
+                    let name = if undo { before } else { after };
+                    if entities.is_alive(entity) {
+                        match name {
+                            Some(name) => {
+                                let _ = names.insert(entity, Named::new(name.clone()));
+                            }
+                            None => {
+                                names.remove(entity);
+                            }
+                        }
+                    }
+                }
+                Change::Hidden { before, after } => {
+                    let hide = if undo { before } else { after };
+                    if entities.is_alive(entity) {
+                        if hide {
+                            let _ = hidden.insert(entity, HiddenPropagate::default());
+                        } else {
+                            hidden.remove(entity);
+                        }
+                    }
+                }
+                Change::Camera { ref before, ref after } => {
+                    let (camera, auto_fov) = if undo { before } else { after };
+                    if entities.is_alive(entity) {
+                        let _ = cameras.insert(entity, camera.clone());
+                        if let Some(auto_fov) = auto_fov {
+                            let _ = auto_fovs.insert(entity, auto_fov.clone());
+                        }
+                    }
+                }
+                Change::Tint { before, after } => {
+                    let tint = if undo { before } else { after };
+                    if entities.is_alive(entity) {
+                        match tint {
+                            Some(tint) => {
+                                let _ = tints.insert(entity, tint);
+                            }
+                            None => {
+                                tints.remove(entity);
+                            }
+                        }
+                    }
+                }
+                Change::Transparent { before, after } => {
+                    let transparent = if undo { before } else { after };
+                    if entities.is_alive(entity) {
+                        if transparent {
+                            let _ = transparents.insert(entity, Transparent);
+                        } else {
+                            transparents.remove(entity);
+                        }
+                    }
+                }
+                Change::Material { ref before, ref after } => {
+                    let (material, material_factors) = if undo { before } else { after };
+                    if entities.is_alive(entity) {
+                        let _ = materials.insert(entity, material.clone());
+                        factors.restore(material, *material_factors);
+                    }
+                }
+                Change::Create(ref snapshot) | Change::Delete(ref snapshot) => {
+                    let create = match edit.change {
+                        Change::Create(_) => !undo,
+                        _ => undo,
+                    };
+                    if create {
+                        let mut builder = entities
+                            .build_entity()
+                            .with(snapshot.transform.clone(), &mut transforms);
+                        if let Some(ref light) = snapshot.light {
+                            builder = builder.with(light.clone(), &mut lights);
+                        }
+                        if let Some(ref name) = snapshot.name {
+                            builder = builder.with(Named::new(name.clone()), &mut names);
+                        }
+                        // The parent may have been recreated by an earlier edit of this command.
+                        let parent = snapshot.parent.map(|parent| {
+                            remaps
+                                .iter()
+                                .find(|(old, _)| *old == parent)
+                                .map_or(parent, |(_, new)| *new)
+                        });
+                        if let Some(parent) = parent.filter(|parent| entities.is_alive(*parent)) {
+                            builder = builder.with(Parent { entity: parent }, &mut parents);
+                        }
+                        if let Some(index) = snapshot.prefab_index {
+                            builder = builder.with(index, &mut indices);
+                        }
+                        remaps.push((entity, builder.build()));
+                    } else {
+                        let _ = entities.delete(entity);
+                    }
+                }
+            }
+        };
+        if undo {
+            command.edits.iter().rev().for_each(&mut apply);
+            history.redo.push(command);
+        } else {
+            command.edits.iter().for_each(&mut apply);
+            history.undo.push(command);
+        }
+        for (old, new) in remaps {
+            history.remap(old, new);
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct HistorySystemDesc;
+impl<'a, 'b> SystemDesc<'a, 'b, HistorySystem> for HistorySystemDesc {
+    fn build(self, world: &mut World) -> HistorySystem {
+        <HistorySystem as System<'_>>::SystemData::setup(world);
+
+        let event_reader = Write::<EventChannel<FilteredInputEvent>>::fetch(world).register_reader();
+
+        HistorySystem {
+            event_reader,
+            scene: None,
+        }
+    }
+}
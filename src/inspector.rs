@@ -0,0 +1,339 @@
+//! Component editor for the selected entity.
+use amethyst::{
+    assets::{AssetLoaderSystemData, AssetStorage, Handle, Loader},
+    core::{
+        ecs::{Entities, Read, ReadExpect, System, Write, WriteStorage},
+        math::Vector3,
+        Transform,
+    },
+    renderer::{
+        camera::Projection,
+        light::Light,
+        palette::Srgba,
+        resources::Tint,
+        types::Texture,
+        Camera, Material, Transparent,
+    },
+    utils::auto_fov::AutoFov,
+};
+use amethyst_imgui::imgui;
+
+use crate::{
+    history::{Edit, History},
+    materials::{self, MaterialFactors},
+    ui::{self, LightTy},
+};
+
+/// Shows the components of `UIState::selected` that can be edited, applying changes immediately.
+///
+/// Edits are recorded in the `History`; material edits once the edited material is loaded.
+#[derive(Default, Debug)]
+pub struct InspectorSystem;
+
+impl<'a> System<'a> for InspectorSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Read<'a, ui::UIState>,
+        Write<'a, History>,
+        Write<'a, MaterialFactors>,
+        Entities<'a>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, Light>,
+        WriteStorage<'a, Camera>,
+        WriteStorage<'a, AutoFov>,
+        WriteStorage<'a, Tint>,
+        WriteStorage<'a, Transparent>,
+        WriteStorage<'a, Handle<Material>>,
+        Read<'a, AssetStorage<Material>>,
+        ReadExpect<'a, Loader>,
+        AssetLoaderSystemData<'a, Texture>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            state,
+            mut history,
+            mut factors,
+            entities,
+            mut transforms,
+            mut lights,
+            mut cameras,
+            mut auto_fovs,
+            mut tints,
+            mut transparents,
+            mut material_handles,
+            material_storage,
+            loader,
+            textures,
+        ): Self::SystemData,
+    ) {
+        use amethyst_imgui::imgui::*;
+
+        let entity = match state.selected.filter(|entity| entities.is_alive(*entity)) {
+            Some(entity) => entity,
+            None => return,
+        };
+
+        let transform_before = transforms.get(entity).cloned();
+        let mut transform = transform_before.as_ref().map(TransformFields::from_transform);
+        let mut light = lights.get(entity).map(LightTy::from_light);
+        let light_before = light;
+        let mut projection = cameras.get(entity).map(|camera| ProjectionFields::from_camera(camera, auto_fovs.get(entity)));
+        let projection_before = projection;
+        let mut tint = tints.get(entity).map(|tint| [tint.0.red, tint.0.green, tint.0.blue, tint.0.alpha]);
+        let tint_before = tint;
+        let mut transparent = transparents.contains(entity);
+        let material = material_handles.get(entity).cloned().and_then(|handle| {
+            let material = material_storage.get(&handle)?.clone();
+            Some((handle, material))
+        });
+        let mut material_factors = material.as_ref().map(|(handle, material)| factors.get(handle, material));
+        let material_users = material
+            .as_ref()
+            .map_or(0, |(handle, _)| materials::users(handle, &material_handles));
+        let mut transparent_changed = false;
+        let mut material_changed = false;
+
+        amethyst_imgui::with(|ui| {
+            Window::new(im_str!("Inspector"))
+                .size([300.0, 400.0], Condition::FirstUseEver)
+                .build(ui, || {
+                    ui.text(&im_str!("Entity {}", entity.id()));
+                    if let Some(ref mut transform) = transform {
+                        if ui.collapsing_header(im_str!("Transform")).default_open(true).build() {
+                            transform.ui(ui);
+                        }
+                    }
+                    if let Some(ref mut light) = light {
+                        if ui.collapsing_header(im_str!("Light")).default_open(true).build() {
+                            let id = ui.push_id("light");
+                            ui::light_properties_ui(ui, light);
+                            id.pop(ui);
+                        }
+                    }
+                    if let Some(ref mut projection) = projection {
+                        if ui.collapsing_header(im_str!("Camera")).default_open(true).build() {
+                            projection.ui(ui);
+                        }
+                    }
+                    if ui.collapsing_header(im_str!("Rendering")).default_open(true).build() {
+                        let mut has_tint = tint.is_some();
+                        if ui.checkbox(im_str!("Tint"), &mut has_tint) {
+                            tint = if has_tint { Some([1.0, 1.0, 1.0, 1.0]) } else { None };
+                        }
+                        if let Some(ref mut tint) = tint {
+                            ColorEdit::new(im_str!("Tint color"), tint).build(ui);
+                        }
+                        transparent_changed = ui.checkbox(im_str!("Transparent"), &mut transparent);
+                    }
+                    if let Some(ref mut material_factors) = material_factors {
+                        if ui.collapsing_header(im_str!("Material")).default_open(true).build() {
+                            let (handle, _) = material.as_ref().unwrap();
+                            ui.text_disabled(&im_str!("Material {}, used by {} entities", handle.id(), material_users));
+                            let id = ui.push_id("material");
+                            material_changed = materials::factors_ui(ui, material_factors);
+                            id.pop(ui);
+                        }
+                    }
+                });
+        });
+
+        if let (Some(before), Some(transform)) = (transform_before, transform) {
+            if TransformFields::from_transform(&before) != transform {
+                let mut after = before.clone();
+                transform.apply(&mut after);
+                let _ = transforms.insert(entity, after.clone());
+                history.record(
+                    format!("Transform entity {}", entity.id()),
+                    vec![Edit::transform(entity, before, after)],
+                );
+            }
+        }
+        if light != light_before {
+            let before = lights.get(entity).cloned();
+            if let (Some(before), Some(after)) = (before, light.and_then(|light| light.to_light())) {
+                let _ = lights.insert(entity, after.clone());
+                history.record(format!("Edit light {}", entity.id()), vec![Edit::light(entity, before, after)]);
+            }
+        }
+        if projection != projection_before {
+            if let (Some(projection), Some(camera)) = (projection, cameras.get_mut(entity)) {
+                let before = (camera.clone(), auto_fovs.get(entity).cloned());
+                projection.apply(camera, auto_fovs.get_mut(entity));
+                let after = (camera.clone(), auto_fovs.get(entity).cloned());
+                history.record(format!("Edit camera {}", entity.id()), vec![Edit::camera(entity, before, after)]);
+            }
+        }
+        if tint != tint_before {
+            let before = tints.get(entity).cloned();
+            let after = tint.map(|[r, g, b, a]| Tint(Srgba::new(r, g, b, a)));
+            match after {
+                Some(after) => {
+                    let _ = tints.insert(entity, after);
+                }
+                None => {
+                    tints.remove(entity);
+                }
+            }
+            history.record(format!("Tint entity {}", entity.id()), vec![Edit::tint(entity, before, after)]);
+        }
+        if transparent_changed {
+            if transparent {
+                let _ = transparents.insert(entity, Transparent);
+            } else {
+                transparents.remove(entity);
+            }
+            history.record(
+                format!("Transparency of entity {}", entity.id()),
+                vec![Edit::transparent(entity, !transparent, transparent)],
+            );
+        }
+        if let (true, Some((handle, material)), Some(after)) = (material_changed, material, material_factors) {
+            let before = factors.get(&handle, &material);
+            let edited = materials::apply_factors(&factors.latest(&handle, &material), &before, &after, &textures);
+            factors.replace(&handle, edited, after, &loader, &material_storage);
+        }
+    }
+}
+
+/// Editable copy of a `Transform`, with the rotation as yaw, pitch and roll in degrees.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct TransformFields {
+    translation: [f32; 3],
+    rotation: [f32; 3],
+    scale: [f32; 3],
+}
+
+impl TransformFields {
+    fn from_transform(transform: &Transform) -> Self {
+        let translation = transform.translation();
+        let scale = transform.scale();
+        Self {
+            translation: [translation.x, translation.y, translation.z],
+            rotation: ui::euler_from_rotation(transform.rotation()),
+            scale: [scale.x, scale.y, scale.z],
+        }
+    }
+
+    /// Writes the fields that differ from `transform` into it, so untouched rotations keep their exact value.
+    fn apply(&self, transform: &mut Transform) {
+        let current = Self::from_transform(transform);
+        if self.translation != current.translation {
+            transform.set_translation(Vector3::from(self.translation));
+        }
+        if self.rotation != current.rotation {
+            transform.set_rotation(ui::rotation_from_euler(self.rotation));
+        }
+        if self.scale != current.scale {
+            transform.set_scale(Vector3::from(self.scale));
+        }
+    }
+
+    fn ui(&mut self, ui: &imgui::Ui) {
+        use amethyst_imgui::imgui::*;
+        ui.drag_float3(im_str!("Position"), &mut self.translation).speed(0.05).build();
+        ui.drag_float3(im_str!("Yaw/Pitch/Roll"), &mut self.rotation).speed(0.5).build();
+        ui.drag_float3(im_str!("Scale"), &mut self.scale).speed(0.01).build();
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ProjectionFields {
+    /// Angles in degrees, the horizontal one is driven by `AutoFov` if the camera has one.
+    Perspective {
+        fov: f32,
+        auto_fov: bool,
+        near: f32,
+        far: f32,
+    },
+    Orthographic {
+        left: f32,
+        right: f32,
+        bottom: f32,
+        top: f32,
+        near: f32,
+        far: f32,
+    },
+    Custom,
+}
+
+impl ProjectionFields {
+    fn from_camera(camera: &Camera, auto_fov: Option<&AutoFov>) -> Self {
+        match *camera.projection() {
+            Projection::Perspective(ref p) => ProjectionFields::Perspective {
+                fov: auto_fov.map_or(p.fovy(), |auto_fov| auto_fov.base_fovx()).to_degrees(),
+                auto_fov: auto_fov.is_some(),
+                near: p.near(),
+                far: p.far(),
+            },
+            Projection::Orthographic(ref o) => ProjectionFields::Orthographic {
+                left: o.left(),
+                right: o.right(),
+                bottom: o.bottom(),
+                top: o.top(),
+                near: o.near(),
+                far: o.far(),
+            },
+            _ => ProjectionFields::Custom,
+        }
+    }
+
+    fn apply(&self, camera: &mut Camera, auto_fov: Option<&mut AutoFov>) {
+        match *self {
+            ProjectionFields::Perspective { fov, near, far, .. } => {
+                let aspect = camera.projection().as_perspective().map_or(1.0, |p| p.aspect());
+                match auto_fov {
+                    Some(auto_fov) => {
+                        auto_fov.set_base_fovx(fov.to_radians());
+                        let fovy = camera.projection().as_perspective().map_or(fov.to_radians(), |p| p.fovy());
+                        camera.set_projection(Projection::perspective(aspect, fovy, near, far));
+                    }
+                    None => camera.set_projection(Projection::perspective(aspect, fov.to_radians(), near, far)),
+                }
+            }
+            ProjectionFields::Orthographic {
+                left,
+                right,
+                bottom,
+                top,
+                near,
+                far,
+            } => camera.set_projection(Projection::orthographic(left, right, bottom, top, near, far)),
+            ProjectionFields::Custom => {}
+        }
+    }
+
+    fn ui(&mut self, ui: &imgui::Ui) {
+        use amethyst_imgui::imgui::*;
+        match *self {
+            ProjectionFields::Perspective {
+                ref mut fov,
+                auto_fov,
+                ref mut near,
+                ref mut far,
+            } => {
+                let label = if auto_fov { im_str!("Horizontal FOV") } else { im_str!("Vertical FOV") };
+                Slider::new(label, 10.0..=150.0).build(ui, fov);
+                ui.drag_float(im_str!("Near"), near).speed(0.01).min(0.001).build();
+                ui.drag_float(im_str!("Far"), far).speed(1.0).min(*near + 0.001).build();
+            }
+            ProjectionFields::Orthographic {
+                ref mut left,
+                ref mut right,
+                ref mut bottom,
+                ref mut top,
+                ref mut near,
+                ref mut far,
+            } => {
+                ui.drag_float(im_str!("Left"), left).speed(0.05).build();
+                ui.drag_float(im_str!("Right"), right).speed(0.05).build();
+                ui.drag_float(im_str!("Bottom"), bottom).speed(0.05).build();
+                ui.drag_float(im_str!("Top"), top).speed(0.05).build();
+                ui.drag_float(im_str!("Near"), near).speed(0.01).build();
+                ui.drag_float(im_str!("Far"), far).speed(1.0).build();
+            }
+            ProjectionFields::Custom => ui.text_disabled(im_str!("Custom projection matrix.")),
+        }
+    }
+}
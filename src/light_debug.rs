@@ -0,0 +1,151 @@
+//! Debug line outlines of area lights.
+use amethyst::{
+    core::{
+        ecs::{Join, Read, ReadStorage, System, Write},
+        math::{Matrix4, Point3, Vector3},
+        Transform,
+    },
+    renderer::{
+        debug_drawing::DebugLines,
+        light::{AreaLight, Light},
+        palette::Srgba,
+    },
+};
+use std::f32::consts::PI;
+
+use crate::ui::UIState;
+
+const CIRCLE_SEGMENTS: usize = 32;
+const NORMAL_LENGTH: f32 = 1.0;
+
+/// Draws the outline of every area light plus an arrow along its emitting side(s).
+///
+/// Shapes are drawn in the light's local space, so they follow its `Transform` scale.
+#[derive(Default, Debug)]
+pub struct LightDebugSystem;
+
+impl<'a> System<'a> for LightDebugSystem {
+    type SystemData = (
+        Read<'a, UIState>,
+        Write<'a, DebugLines>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, Light>,
+    );
+
+    fn run(&mut self, (state, mut debug_lines, transforms, lights): Self::SystemData) {
+        if !state.show_light_shapes {
+            return;
+        }
+        for (transform, light) in (&transforms, &lights).join() {
+            let area = match *light {
+                Light::Area(ref area) => area,
+                _ => continue,
+            };
+            let matrix = transform.global_matrix();
+            let (color, two_sided) = match *area {
+                AreaLight::Disk(ref l) => (l.diffuse_color, Some(l.two_sided)),
+                AreaLight::Sphere(ref l) => (l.diffuse_color, None),
+                AreaLight::Rectangle(ref l) => (l.diffuse_color, Some(l.two_sided)),
+            };
+            let outline = Srgba::new(color.red, color.green, color.blue, 1.0);
+            match *area {
+                AreaLight::Disk(_) => {
+                    draw_circle(&mut debug_lines, matrix, 1.0, |x, y| Vector3::new(x, y, 0.0), outline);
+                }
+                AreaLight::Sphere(ref l) => {
+                    draw_circle(&mut debug_lines, matrix, l.radius, |x, y| Vector3::new(x, y, 0.0), outline);
+                    draw_circle(&mut debug_lines, matrix, l.radius, |x, y| Vector3::new(x, 0.0, y), outline);
+                    draw_circle(&mut debug_lines, matrix, l.radius, |x, y| Vector3::new(0.0, x, y), outline);
+                }
+                AreaLight::Rectangle(_) => {
+                    let corners = [
+                        Vector3::new(-1.0, -1.0, 0.0),
+                        Vector3::new(1.0, -1.0, 0.0),
+                        Vector3::new(1.0, 1.0, 0.0),
+                        Vector3::new(-1.0, 1.0, 0.0),
+                    ];
+                    for (start, end) in corners.iter().zip(corners.iter().cycle().skip(1)) {
+                        draw_local_line(&mut debug_lines, matrix, *start, *end, outline);
+                    }
+                }
+            }
+
+            let arrow = Srgba::new(1.0, 0.9, 0.2, 1.0);
+            if let Some(two_sided) = two_sided {
+                draw_arrow(&mut debug_lines, matrix, -Vector3::z(), arrow);
+                if two_sided {
+                    draw_arrow(&mut debug_lines, matrix, Vector3::z(), arrow);
+                }
+            }
+        }
+    }
+}
+
+fn to_world(matrix: &Matrix4<f32>, local: Vector3<f32>) -> Point3<f32> {
+    matrix.transform_point(&Point3::from(local))
+}
+
+fn draw_local_line(lines: &mut DebugLines, matrix: &Matrix4<f32>, start: Vector3<f32>, end: Vector3<f32>, color: Srgba) {
+    lines.draw_line(to_world(matrix, start), to_world(matrix, end), color);
+}
+
+/// Draws a circle of `radius` in the plane spanned by `plane`, which maps 2D coordinates to local space.
+fn draw_circle<F>(lines: &mut DebugLines, matrix: &Matrix4<f32>, radius: f32, plane: F, color: Srgba)
+where
+    F: Fn(f32, f32) -> Vector3<f32>,
+{
+    let point = |i: usize| {
+        let angle = 2.0 * PI * i as f32 / CIRCLE_SEGMENTS as f32;
+        plane(radius * angle.cos(), radius * angle.sin())
+    };
+    for i in 0..CIRCLE_SEGMENTS {
+        draw_local_line(lines, matrix, point(i), point(i + 1), color);
+    }
+}
+
+/// Draws a wireframe sphere around a world space `center`.
+pub fn draw_wire_sphere(lines: &mut DebugLines, center: Point3<f32>, radius: f32, color: Srgba) {
+    let matrix = Matrix4::new_translation(&center.coords);
+    draw_circle(lines, &matrix, radius, |x, y| Vector3::new(x, y, 0.0), color);
+    draw_circle(lines, &matrix, radius, |x, y| Vector3::new(x, 0.0, y), color);
+    draw_circle(lines, &matrix, radius, |x, y| Vector3::new(0.0, x, y), color);
+}
+
+/// Draws an arrow from the light's origin along the local `direction`.
+///
+/// The arrow has a fixed world length so it stays readable for scaled lights.
+fn draw_arrow(lines: &mut DebugLines, matrix: &Matrix4<f32>, direction: Vector3<f32>, color: Srgba) {
+    let start = to_world(matrix, Vector3::zeros());
+    let direction = matrix.transform_vector(&direction);
+    if direction.norm_squared() < std::f32::EPSILON {
+        return;
+    }
+    draw_world_arrow(lines, start, start + direction.normalize() * NORMAL_LENGTH, color);
+}
+
+/// Draws an arrow between two world space points.
+pub fn draw_world_arrow(lines: &mut DebugLines, start: Point3<f32>, end: Point3<f32>, color: Srgba) {
+    let direction = end - start;
+    let length = direction.norm();
+    if length < std::f32::EPSILON {
+        return;
+    }
+    let direction = direction / length;
+    lines.draw_line(start, end, color);
+
+    let side = if direction.y.abs() > 0.99 { Vector3::x() } else { Vector3::y() };
+    let side = direction.cross(&side).normalize() * 0.1 * length;
+    let back = end - direction * 0.2 * length;
+    lines.draw_line(end, back + side, color);
+    lines.draw_line(end, back - side, color);
+}
+
+/// Draws a circle of `radius` around a world space `center`, in the plane orthogonal to `normal`.
+pub fn draw_world_circle(lines: &mut DebugLines, center: Point3<f32>, normal: Vector3<f32>, radius: f32, color: Srgba) {
+    let normal = normal.normalize();
+    let helper = if normal.y.abs() > 0.99 { Vector3::x() } else { Vector3::y() };
+    let u = normal.cross(&helper).normalize();
+    let v = normal.cross(&u);
+    let matrix = Matrix4::new_translation(&center.coords);
+    draw_circle(lines, &matrix, radius, |x, y| u * x + v * y, color);
+}
@@ -0,0 +1,226 @@
+//! Emissive meshes that make the surface of area lights visible.
+use amethyst::{
+    assets::{AssetLoaderSystemData, Handle},
+    core::{
+        ecs::{Component, DenseVecStorage, Entities, Entity, Join, Read, ReadExpect, ReadStorage, System, WriteStorage},
+        math::Vector3,
+        Parent, Transform,
+    },
+    renderer::{
+        light::{AreaLight, Light},
+        palette::LinSrgba,
+        rendy::{
+            mesh::{Normal, Position, Tangent, TexCoord},
+            texture::palette::load_from_linear_rgba,
+        },
+        shape::Shape,
+        types::{Mesh, Texture},
+        Material, MaterialDefaults,
+    },
+};
+use std::{collections::HashMap, f32::consts::PI};
+
+use crate::ui::UIState;
+
+/// Marks an entity as the visible surface of the area light `light`.
+#[derive(Clone, Copy, Debug)]
+pub struct LightProxy {
+    pub light: Entity,
+}
+
+impl Component for LightProxy {
+    type Storage = DenseVecStorage<Self>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum ProxyShape {
+    Disk,
+    Sphere,
+    Rectangle,
+}
+
+/// Everything a proxy is built from; proxies are updated whenever this changes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct ProxyKey {
+    shape: ProxyShape,
+    two_sided: bool,
+    radius: f32,
+    emission: [f32; 3],
+}
+
+impl ProxyKey {
+    fn from_light(light: &Light) -> Option<Self> {
+        let area = match *light {
+            Light::Area(ref area) => area,
+            _ => return None,
+        };
+        let (shape, two_sided, radius, color, intensity) = match *area {
+            AreaLight::Disk(ref l) => (ProxyShape::Disk, l.two_sided, 1.0, l.diffuse_color, l.intensity.get()),
+            AreaLight::Sphere(ref l) => (ProxyShape::Sphere, false, l.radius, l.diffuse_color, l.intensity.get()),
+            AreaLight::Rectangle(ref l) => (ProxyShape::Rectangle, l.two_sided, 1.0, l.diffuse_color, l.intensity.get()),
+        };
+        Some(Self {
+            shape,
+            two_sided,
+            radius,
+            emission: [color.red * intensity, color.green * intensity, color.blue * intensity],
+        })
+    }
+
+    /// Whether the proxy entities have to be rebuilt, as opposed to only swapping their material.
+    fn needs_rebuild(&self, other: &Self) -> bool {
+        self.shape != other.shape || self.two_sided != other.two_sided || self.radius != other.radius
+    }
+}
+
+type ShapeVertex = (Vec<Position>, Vec<Normal>, Vec<Tangent>, Vec<TexCoord>);
+
+/// Spawns a companion mesh for every area light, parented to the light so it follows its `Transform`.
+///
+/// Disks and rectangles face the light's emitting side (-Z), with a second mesh facing +Z when
+/// the light is two sided. The emission of the material tracks `diffuse_color * intensity`.
+#[derive(Default)]
+pub struct LightProxySystem {
+    proxies: HashMap<Entity, (ProxyKey, Vec<Entity>)>,
+    meshes: HashMap<ProxyShape, Handle<Mesh>>,
+    black: Option<Handle<Texture>>,
+}
+
+impl<'a> System<'a> for LightProxySystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Read<'a, UIState>,
+        Entities<'a>,
+        ReadStorage<'a, Light>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, Parent>,
+        WriteStorage<'a, LightProxy>,
+        WriteStorage<'a, Handle<Mesh>>,
+        WriteStorage<'a, Handle<Material>>,
+        AssetLoaderSystemData<'a, Mesh>,
+        AssetLoaderSystemData<'a, Texture>,
+        AssetLoaderSystemData<'a, Material>,
+        ReadExpect<'a, MaterialDefaults>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            state,
+            entities,
+            lights,
+            mut transforms,
+            mut parents,
+            mut proxies,
+            mut meshes,
+            mut materials,
+            mesh_loader,
+            texture_loader,
+            material_loader,
+            material_defaults,
+        ): Self::SystemData,
+    ) {
+        let wanted = if state.show_light_proxies {
+            (&entities, &lights)
+                .join()
+                .filter_map(|(entity, light)| ProxyKey::from_light(light).map(|key| (entity, key)))
+                .collect::<HashMap<_, _>>()
+        } else {
+            HashMap::new()
+        };
+
+        let stale = self
+            .proxies
+            .iter()
+            .filter(|(light, (key, _))| wanted.get(*light).map_or(true, |wanted| wanted.needs_rebuild(key)))
+            .map(|(light, _)| *light)
+            .collect::<Vec<_>>();
+        for light in stale {
+            for proxy in self.proxies.remove(&light).unwrap().1 {
+                let _ = entities.delete(proxy);
+            }
+        }
+
+        for (light, key) in wanted {
+            if let Some((current, _)) = self.proxies.get(&light) {
+                if *current == key {
+                    continue;
+                }
+            }
+
+            let black = self
+                .black
+                .get_or_insert_with(|| {
+                    texture_loader.load_from_data(load_from_linear_rgba(LinSrgba::new(0.0, 0.0, 0.0, 1.0)).into(), ())
+                })
+                .clone();
+            let emission = texture_loader.load_from_data(
+                load_from_linear_rgba(LinSrgba::new(key.emission[0], key.emission[1], key.emission[2], 1.0)).into(),
+                (),
+            );
+            let material = material_loader.load_from_data(
+                Material {
+                    albedo: black,
+                    emission,
+                    ..material_defaults.0.clone()
+                },
+                (),
+            );
+
+            // Only the emission changed, keep the existing entities.
+            if let Some((current, spawned)) = self.proxies.get_mut(&light) {
+                *current = key;
+                for proxy in spawned.iter() {
+                    let _ = materials.insert(*proxy, material.clone());
+                }
+                continue;
+            }
+
+            let mesh = self
+                .meshes
+                .entry(key.shape)
+                .or_insert_with(|| {
+                    let shape = match key.shape {
+                        ProxyShape::Disk => Shape::Circle(32),
+                        ProxyShape::Sphere => Shape::Sphere(32, 32),
+                        ProxyShape::Rectangle => Shape::Plane(None),
+                    };
+                    mesh_loader.load_from_data(shape.generate::<ShapeVertex>(None).into(), ())
+                })
+                .clone();
+
+            let mut sides = Vec::new();
+            match key.shape {
+                ProxyShape::Sphere => {
+                    let mut transform = Transform::default();
+                    transform.set_scale(Vector3::new(key.radius, key.radius, key.radius));
+                    sides.push(transform);
+                }
+                ProxyShape::Disk | ProxyShape::Rectangle => {
+                    // The generated shapes face +Z, turn the front side towards -Z.
+                    let mut front = Transform::default();
+                    front.set_rotation_y_axis(PI);
+                    sides.push(front);
+                    if key.two_sided {
+                        sides.push(Transform::default());
+                    }
+                }
+            }
+
+            let spawned = sides
+                .into_iter()
+                .map(|transform| {
+                    entities
+                        .build_entity()
+                        .with(transform, &mut transforms)
+                        .with(Parent { entity: light }, &mut parents)
+                        .with(mesh.clone(), &mut meshes)
+                        .with(material.clone(), &mut materials)
+                        .with(LightProxy { light }, &mut proxies)
+                        .build()
+                })
+                .collect();
+            self.proxies.insert(light, (key, spawned));
+        }
+    }
+}
@@ -6,7 +6,7 @@ use amethyst::{
         EndControl, VertexSkinningBundle,
     },
     assets::{
-        AssetLoaderSystemData, AssetStorage, Completion, HotReloadBundle, Handle, Loader, PrefabLoader,
+        AssetLoaderSystemData, AssetStorage, Completion, HotReloadBundle, HotReloadStrategy, Handle, Loader, PrefabLoader,
         PrefabLoaderSystemDesc, ProgressCounter, RonFormat, Prefab
     },
     controls::{FlyControlBundle, FlyControlTag, FlyMovementSystemDesc, MouseFocusUpdateSystemDesc, CursorHideSystemDesc, HideCursor, WindowFocus },
@@ -17,7 +17,7 @@ use amethyst::{
         },
         shrev::{EventChannel, ReaderId},
         math::{Unit, UnitQuaternion, Quaternion, Vector3, U1, U3},
-        Time, Transform, TransformBundle, SystemDesc, Parent
+        HideHierarchySystemDesc, Time, Transform, TransformBundle, SystemDesc, Parent
     },
     error::Error,
     gltf::GltfSceneLoaderSystemDesc,
@@ -43,6 +43,7 @@ use amethyst::{
         RenderFlat2D, RenderFlat3D, RenderPbr3D, RenderShaded3D, RenderSkybox, RenderToWindow,
         RenderingBundle, SpriteRender, SpriteSheet, SpriteSheetFormat, Transparent,
     },
+    window::DisplayConfig,
     utils::{
         application_root_dir,
         auto_fov::{AutoFov, AutoFovSystem},
@@ -53,40 +54,123 @@ use amethyst::{
 use std::vec::Vec;
 use amethyst::winit::{self, Event, DeviceEvent, WindowEvent, ElementState, MouseButton};
 use amethyst_imgui::RenderImgui;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::collections::HashMap;
 use amethyst_derive::SystemDesc;
 use derive_new::new;
 
-// use amethyst_inspector::{Inspector, InspectorHierarchy, inspector};
-
 use prefab_data::{AnimationMarker, Scene, ScenePrefabData, SpriteAnimationId};
 use filtered_input::{FilterInputSystemDesc, FilteredInputEvent};
+use scenes::{SceneInfo, SceneMap};
+use cli::LaunchOptions;
+use capture::{CaptureHotkeySystemDesc, CaptureRequest, CaptureStatus, FrameCapture};
+use scene_save::SceneSaveSystem;
+use ui::{UIState, UISystem};
+use light_debug::LightDebugSystem;
+use light_proxy::LightProxySystem;
+use picking::PickingSystemDesc;
+use gizmo::{Gizmo, GizmoSystemDesc};
+use history::HistorySystemDesc;
+use outliner::OutlinerSystem;
+use inspector::InspectorSystem;
+use materials::MaterialBrowserSystem;
+use material_balls::{MaterialBallSettings, MaterialBallSystem};
+use bookmarks::BookmarkSystemDesc;
+use orbit::OrbitCameraSystemDesc;
+use camera_path::CameraPathSystem;
+use fixed_step::FixedStep;
+use perf::{PerfOverlaySystem, SystemTimings, Timed, TimedDesc};
+use benchmark::{Benchmark, BenchmarkOptions};
+use screenshot::{ScreenshotSystemDesc, Screenshots};
+use readback::RenderScreenshots;
 #[cfg(feature = "profiler")]
 use thread_profiler::profile_scope;
 
 mod prefab_data;
 mod filtered_input;
+mod scenes;
+mod cli;
+mod capture;
+mod scene_save;
+mod ui;
+mod light_debug;
+mod light_proxy;
+mod picking;
+mod gizmo;
+mod history;
+mod outliner;
+mod materials;
+mod inspector;
+mod material_balls;
+mod bookmarks;
+mod orbit;
+mod camera_path;
+mod fixed_step;
+mod perf;
+mod benchmark;
+mod screenshot;
+mod readback;
+
+/// Background of the window and of screenshots.
+const CLEAR_COLOR: [f32; 4] = [0.34, 0.36, 0.52, 1.0];
 
 struct Lightroom {
     initialised: bool,
     progress: Option<ProgressCounter>,
     scene: usize,
-    scene_root: Option<Entity>
+    scene_root: Option<Entity>,
+    scenes: Vec<SceneInfo>,
+    ui_state: UIState,
+    render_mode: RenderMode,
+    capture_prefix: Option<String>,
+    capture: Option<FrameCapture>,
+    fixed_step: FixedStep,
+    benchmark: Option<Benchmark>,
+    screenshot_dir: PathBuf,
 }
 
 impl Lightroom {
-    pub fn new(scene: usize) -> Self {
+    pub fn new(ui_state: UIState, render_mode: RenderMode, scenes: Vec<SceneInfo>) -> Self {
         Self {
             initialised: false,
             progress: None,
-            scene,
-            scene_root: None
+            scene: ui_state.scene,
+            scene_root: None,
+            scenes,
+            ui_state,
+            render_mode,
+            capture_prefix: None,
+            capture: None,
+            fixed_step: FixedStep::default(),
+            benchmark: None,
+            screenshot_dir: PathBuf::from(screenshot::DEFAULT_DIR),
         }
     }
-}
 
-type SceneMap = HashMap<usize, Handle<Prefab<ScenePrefabData>>>;
+    /// Sets the path prefix RenderDoc writes its captures to.
+    pub fn with_capture_prefix(mut self, prefix: Option<String>) -> Self {
+        self.capture_prefix = prefix;
+        self
+    }
+
+    /// Starts with the given frame stepping instead of the real frame time.
+    pub fn with_fixed_step(mut self, fixed_step: FixedStep) -> Self {
+        self.fixed_step = fixed_step;
+        self
+    }
+
+    /// Runs the benchmark once loading finished instead of waiting for input, then quits.
+    pub fn with_benchmark(mut self, benchmark: Option<Benchmark>) -> Self {
+        self.benchmark = benchmark;
+        self
+    }
+
+    /// Sets the directory screenshots and their metadata are written to.
+    pub fn with_screenshot_dir(mut self, dir: PathBuf) -> Self {
+        self.screenshot_dir = dir;
+        self
+    }
+}
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 enum RenderMode {
@@ -101,6 +185,22 @@ impl Default for RenderMode {
     }
 }
 
+impl RenderMode {
+    pub const ALL: [RenderMode; 3] = [RenderMode::Flat, RenderMode::Shaded, RenderMode::Pbr];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RenderMode::Flat => "flat",
+            RenderMode::Shaded => "shaded",
+            RenderMode::Pbr => "pbr",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().cloned().find(|mode| mode.name().eq_ignore_ascii_case(name))
+    }
+}
+
 impl SimpleState for Lightroom {
     fn on_start(&mut self, data: StateData<'_, GameData<'_, '_>>) {
         #[cfg(feature = "profiler")]
@@ -112,38 +212,40 @@ impl SimpleState for Lightroom {
         let StateData { world, .. } = data;
 
         let mat_defaults = world.read_resource::<MaterialDefaults>().0.clone();
-        world.insert(SceneMap::new());
-        world.insert(UIState::default());
+        world.insert(SceneMap::default());
+        world.insert(self.ui_state.clone());
+        world.insert(MaterialBallSettings::default());
+        world.insert(self.fixed_step.clone());
+        world.insert(Screenshots::new(self.screenshot_dir.clone()));
 
         self.progress = Some(ProgressCounter::default());
 
         world.exec(
-            |(loader, mut scene, mut scene_map): (PrefabLoader<'_, ScenePrefabData>, Write<'_, Scene>, Write<'_, SceneMap>)| {
-                scene_map.insert(0,
-                    loader.load(
-                        Path::new("prefab")
-                            .join("lightroom_0.ron")
-                            .to_string_lossy(),
-                        RonFormat,
-                        self.progress.as_mut().unwrap(),
-                    ),
-                );
-                scene_map.insert(1,
-                    loader.load(
-                        Path::new("prefab")
-                            .join("lightroom_1.ron")
-                            .to_string_lossy(),
-                        RonFormat,
-                        self.progress.as_mut().unwrap(),
-                    ),
-                );
-
-                scene.handle = Some(scene_map.get(&0).unwrap().clone());
+            |(loader, mut scene, mut scene_map, material_balls): (PrefabLoader<'_, ScenePrefabData>, Write<'_, Scene>, Write<'_, SceneMap>, Read<'_, MaterialBallSettings>)| {
+                for info in self.scenes.drain(..) {
+                    let handle = if info.procedural {
+                        // An empty scene keeps the indices of the following scenes intact.
+                        let prefab = material_balls::build_prefab(&material_balls).unwrap_or_else(|e| {
+                            println!("Failed to build {}: {}", info.name, e);
+                            Prefab::new()
+                        });
+                        loader.load_from_data(prefab, self.progress.as_mut().unwrap())
+                    } else {
+                        loader.load(
+                            info.asset_path.as_str(),
+                            RonFormat,
+                            self.progress.as_mut().unwrap(),
+                        )
+                    };
+                    scene_map.insert(info, handle);
+                }
+
+                scene.handle = scene_map.get(self.scene).map(|entry| entry.handle.clone());
             },
         );
         
 
-        // Create the camera
+        // Create the camera, `SceneChangeSystem` moves it to the scene's default pose.
         let mut transform = Transform::default();
         transform.set_translation_xyz(0.0, 2.0, 4.0);
 
@@ -162,7 +264,13 @@ impl SimpleState for Lightroom {
         world.insert(ActiveCamera {
             entity: Some(camera),
         });
-        world.insert(RenderMode::default());
+        world.insert(self.render_mode);
+        world.insert(DebugLines::new());
+
+        let (capture, status) = FrameCapture::new(self.capture_prefix.as_ref().map(String::as_str));
+        self.capture = Some(capture);
+        world.insert(status);
+        world.insert(CaptureRequest::default());
     }
 
     fn handle_event(
@@ -222,7 +330,32 @@ impl SimpleState for Lightroom {
                 self.progress = None;
             }
         }
-        
+
+        // Runs before the dispatcher, so every system of this frame sees the fixed delta.
+        data.world.exec(
+            |(mut fixed_step, mut time, mut hot_reload): (Write<'_, FixedStep>, Write<'_, Time>, Write<'_, HotReloadStrategy>)| {
+                fixed_step.advance(&mut time, &mut hot_reload);
+            },
+        );
+
+        if let (None, Some(benchmark)) = (self.progress.as_ref(), self.benchmark.as_mut()) {
+            match benchmark.update(&mut data.world) {
+                Ok(false) => {}
+                Ok(true) => return Trans::Quit,
+                Err(e) => {
+                    println!("Benchmark failed: {}", e);
+                    return Trans::Quit;
+                }
+            }
+        }
+
+        let requested = std::mem::replace(&mut data.world.write_resource::<CaptureRequest>().frame, false);
+        if requested {
+            if let Some(ref mut capture) = self.capture {
+                capture.trigger(&mut data.world.write_resource::<CaptureStatus>());
+            }
+        }
+
         Trans::None
     }
 }
@@ -261,9 +394,27 @@ fn main() -> amethyst::Result<()> {
     let app_root = application_root_dir()?;
     let assets_dir = app_root.join("resources");
 
+    let mut scenes = scenes::discover_scenes(&assets_dir)?;
+    scenes.push(material_balls::scene_info(&MaterialBallSettings::default()));
+
+    let options = LaunchOptions::parse(std::env::args().skip(1))?;
+    if options.help {
+        cli::print_help(&scenes);
+        return Ok(());
+    }
+    let ui_state = UIState {
+        scene: options.scene_index(&scenes)?,
+        free_camera_movement: options.free_camera,
+        ..Default::default()
+    };
+
     let display_config_path = assets_dir
         .join("config")
         .join("display.ron");
+    let mut display_config = DisplayConfig::load(&display_config_path)?;
+    if let Some(size) = options.size {
+        display_config.dimensions = Some(size);
+    }
 
     let mut bindings = Bindings::new();
     bindings.insert_axis(
@@ -288,10 +439,7 @@ fn main() -> amethyst::Result<()> {
         },
     )?;
 
-    use renderdoc::{RenderDoc, V100, V120};
-        let mut rd: renderdoc::RenderDoc<renderdoc::V120> =
-        renderdoc::RenderDoc::new().expect("Failed to init renderdoc");
-
+    let timings = SystemTimings::default();
     let game_data = GameDataBuilder::default()
         .with(AutoFovSystem::default(), "auto_fov", &[])
         .with_bundle(FpsCounterBundle::default())?
@@ -306,7 +454,7 @@ fn main() -> amethyst::Result<()> {
             &["scene_loader"], // This is important so that entity instantiation is performed in a single frame.
         )
         .with_bundle(InputBundle::<StringBindings>::new().with_bindings(bindings))?
-        .with_system_desc(FilterInputSystemDesc::default(), "input_filter", &["input_system"])
+        .with_system_desc(TimedDesc::new("input_filter", FilterInputSystemDesc::default(), &timings), "input_filter", &["input_system"])
         .with_bundle(HotReloadBundle::default())?
         // .with_bundle(
         //     FlyControlBundle::<StringBindings>::new(
@@ -326,9 +474,10 @@ fn main() -> amethyst::Result<()> {
             "fly_movement",
             &[],
         )
-        .with_system_desc(CustomFreeRotationSystemDesc::new(0.1, 0.1, false),
+        .with_system_desc(TimedDesc::new("gizmo", GizmoSystemDesc::default(), &timings), "gizmo", &["input_filter"])
+        .with_system_desc(TimedDesc::new("free_rotation", CustomFreeRotationSystemDesc::new(0.1, 0.1, false), &timings),
             "free_rotation",
-            &["input_filter"],
+            &["input_filter", "gizmo"],
         )
         .with_system_desc(MouseFocusUpdateSystemDesc::default(),
             "mouse_focus",
@@ -338,27 +487,76 @@ fn main() -> amethyst::Result<()> {
             "cursor_hide",
             &["mouse_focus"],
         )
-        .with_system_desc(SceneChangeSystemDesc::default(), "scene_change", &[])
+        .with_system_desc(TimedDesc::new("scene_change", SceneChangeSystemDesc::default(), &timings), "scene_change", &[])
+        .with_system_desc(TimedDesc::new("picking", PickingSystemDesc::default(), &timings), "picking", &["input_filter", "gizmo"])
         .with_bundle(TransformBundle::new().with_dep(&[
             "fly_movement",
         ]))?
         .with_bundle(VertexSkinningBundle::new().with_dep(&[
             "transform_system",
         ]))?
-        // .with(amethyst_inspector::InspectorHierarchy::<UserData>::default(), "", &[])
-	    // .with(Inspector, "", &[""])
-        .with(UISystem::default(), "imgui_use", &[])
+        .with(Timed::new("imgui_use", UISystem::default(), &timings), "imgui_use", &[])
+        .with_system_desc(TimedDesc::new("capture_hotkey", CaptureHotkeySystemDesc::default(), &timings), "capture_hotkey", &["input_filter"])
+        .with_system_desc(HideHierarchySystemDesc::default(), "hide_hierarchy", &["parent_hierarchy_system"])
+        .with(Timed::new("outliner", OutlinerSystem::default(), &timings), "outliner", &["imgui_use"])
+        .with(Timed::new("inspector", InspectorSystem::default(), &timings), "inspector", &["outliner"])
+        .with(Timed::new("material_browser", MaterialBrowserSystem::default(), &timings), "material_browser", &["inspector"])
+        .with_system_desc(
+            TimedDesc::new("history", HistorySystemDesc::default(), &timings),
+            "history",
+            &["input_filter", "imgui_use", "inspector", "material_browser", "gizmo"],
+        )
+        .with(Timed::new("scene_save", SceneSaveSystem::default(), &timings), "scene_save", &["imgui_use", "history"])
+        .with(Timed::new("material_balls", MaterialBallSystem::default(), &timings), "material_balls", &["imgui_use", "history"])
+        .with_system_desc(
+            TimedDesc::new("bookmarks", BookmarkSystemDesc::new(assets_dir.clone()), &timings),
+            "bookmarks",
+            &["input_filter", "imgui_use", "free_rotation"],
+        )
+        .with_system_desc(
+            TimedDesc::new("orbit_camera", OrbitCameraSystemDesc::default(), &timings),
+            "orbit_camera",
+            &["input_filter", "imgui_use", "gizmo", "bookmarks"],
+        )
+        .with(
+            Timed::new("camera_path", CameraPathSystem::new(assets_dir.clone()), &timings),
+            "camera_path",
+            &["imgui_use", "orbit_camera"],
+        )
+        .with(Timed::new("light_debug", LightDebugSystem::default(), &timings), "light_debug", &["imgui_use", "transform_system"])
+        .with(Timed::new("light_proxy", LightProxySystem::default(), &timings), "light_proxy", &["imgui_use"])
+        .with(PerfOverlaySystem::new(&timings), "perf_overlay", &["imgui_use"])
+        .with_system_desc(TimedDesc::new("screenshot", ScreenshotSystemDesc::default(), &timings), "screenshot", &["imgui_use", "input_filter"])
         .with_bundle(
             RenderingBundle::<DefaultBackend>::new()
                 .with_plugin(
-                    RenderToWindow::from_config_path(display_config_path)?
-                    .with_clear([0.34, 0.36, 0.52, 1.0]),
+                    RenderToWindow::from_config(display_config)
+                    .with_clear(CLEAR_COLOR),
                 )
                 .with_plugin(RenderSwitchable3D::default())
+                .with_plugin(RenderScreenshots::default())
+                .with_plugin(RenderDebugLines::default())
                 .with_plugin(RenderImgui::<StringBindings>::default()),
         )?;
 
-    let mut game = Application::new(assets_dir, Lightroom::new(0), game_data)?;
+    // Benchmarks always step at a fixed rate, so camera paths pass the same views in every run.
+    let fixed_step = match (options.fixed_step, options.benchmark.is_some()) {
+        (None, true) => Some(fixed_step::DEFAULT_RATE),
+        (rate, _) => rate,
+    };
+    let benchmark = options.benchmark.clone().map(|output| {
+        Benchmark::new(BenchmarkOptions {
+            output,
+            warmup: options.warmup.unwrap_or(benchmark::DEFAULT_WARMUP),
+            frames: options.frames.unwrap_or(benchmark::DEFAULT_FRAMES),
+        })
+    });
+    let lightroom = Lightroom::new(ui_state, options.mode.unwrap_or_default(), scenes)
+        .with_capture_prefix(options.capture_prefix.clone())
+        .with_fixed_step(FixedStep::new(fixed_step))
+        .with_benchmark(benchmark)
+        .with_screenshot_dir(options.screenshot_dir.clone().unwrap_or_else(|| app_root.join(screenshot::DEFAULT_DIR)));
+    let mut game = Application::new(assets_dir, lightroom, game_data)?;
     game.run();
     Ok(())
 }
@@ -412,17 +610,29 @@ impl<'a> System<'a> for SceneChangeSystem {
         Write<'a, Scene>,
         Read<'a, SceneMap>,
         WriteStorage<'a, Handle<Prefab<ScenePrefabData>>>,
+        Read<'a, ActiveCamera>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, AutoFov>,
     );
 
-    fn run(&mut self, (entities, ui_state, mut scene, scene_map, mut prefabs): Self::SystemData) {
+    fn run(&mut self, (entities, ui_state, mut scene, scene_map, mut prefabs, camera, mut transforms, mut auto_fovs): Self::SystemData) {
         if scene.scene.is_none() || scene.scene.unwrap() != ui_state.scene {
+            let entry = match scene_map.get(ui_state.scene) {
+                Some(entry) => entry,
+                None => return,
+            };
             scene.scene = Some(ui_state.scene);
-            let scene_handle = scene_map.get(&ui_state.scene).unwrap().clone();
             if scene.entity.is_some() {
                 entities.delete(scene.entity.unwrap());
             }
-            println!("Creating new parent entity for scene {}.", scene.scene.unwrap());
-            scene.entity = Some(entities.build_entity().with(scene_handle, &mut prefabs).build());
+            println!("Creating new parent entity for scene {}.", entry.info.name);
+            scene.entity = Some(entities.build_entity().with(entry.handle.clone(), &mut prefabs).build());
+
+            if let Some(camera) = camera.entity {
+                if let (Some(transform), Some(auto_fov)) = (transforms.get_mut(camera), auto_fovs.get_mut(camera)) {
+                    entry.info.camera.apply(transform, auto_fov);
+                }
+            }
         }
     }
 }
@@ -443,10 +653,11 @@ impl<'a> System<'a> for CustomFreeRotationSystem {
         ReadStorage<'a, FlyControlTag>,
         Read<'a, WindowFocus>,
         Read<'a, HideCursor>,
-        Read<'a, UIState>
+        Read<'a, UIState>,
+        Read<'a, Gizmo>,
     );
 
-    fn run(&mut self, (events, mut transform, tag, focus, hide, state): Self::SystemData) {
+    fn run(&mut self, (events, mut transform, tag, focus, hide, state, gizmo): Self::SystemData) {
         #[cfg(feature = "profiler")]
         profile_scope!("free_rotation_system");
 
@@ -456,7 +667,7 @@ impl<'a> System<'a> for CustomFreeRotationSystem {
                 if let FilteredInputEvent::Free ( ref event ) = *event {
                     match *event {
                     InputEvent::MouseMoved { delta_x, delta_y } => {
-                        if focused && hide.hide && (state.free_camera_movement || self.mouse_down) {
+                        if focused && hide.hide && !gizmo.dragging && (state.free_camera_movement || self.mouse_down) {
                             for (transform, _) in (&mut transform, &tag).join() {
                                 transform.append_rotation_x_axis(
                                     (-(delta_y as f32) * self.sensitivity_y).to_radians(),
@@ -475,215 +686,3 @@ impl<'a> System<'a> for CustomFreeRotationSystem {
         }
     }
 }
-
-// inspector![
-//     Parent,
-// 	Transform,
-// 	Light,
-// ];
-
-
-#[derive(Default, Debug, Clone)]
-pub struct UIState {
-    free_camera_movement: bool,
-    scene: usize,
-}
-#[derive(PartialEq, Clone, Debug, Copy)]
-pub struct LightTy {
-    kind: usize,
-    intensity: f32,
-    unit_type: usize,
-}
-#[derive(PartialEq, Clone, Debug, Copy)]
-pub struct LightSync {
-    entity: Entity,
-    translation: [f32; 3],
-    rotation: [f32; 4],
-    scale: [f32; 2],
-    light: LightTy
-}
-
-#[derive(Default, Clone, Copy)]
-pub struct UISystem;
-impl<'s> amethyst::ecs::System<'s> for UISystem {
-    type SystemData = (
-        Write<'s, UIState>,
-        Entities<'s>,
-        WriteStorage<'s, Transform>,
-        WriteStorage<'s, Light>
-
-    );
-    fn run(&mut self, (mut state, entities, mut transforms, mut lights): Self::SystemData) {
-        use amethyst_imgui::imgui::*;
-        use amethyst::renderer::light::AreaLight;
-        let mut lights_cache = (&*entities, &transforms, &lights).join().map(|(e, t, l)| {
-            let trans = t.translation();
-            let rot = t.rotation().into_inner();
-
-            let scale = t.scale();
-            LightSync{
-                entity: e,
-                translation: [trans[0], trans[1], trans[2]],
-                rotation: [rot.coords[0], rot.coords[1], rot.coords[2], rot.coords[3]],
-                scale: [scale[0], scale[1]],
-                light: if let Light::Area(ref light) = *l {
-                        match *light {
-                            AreaLight::Disk(ref l) => 
-                                LightTy {
-                                    kind: 1,
-                                    intensity: l.intensity.get(),
-                                    unit_type: 0
-                                }
-                            ,
-                            AreaLight::Sphere(ref l) => 
-                                LightTy{
-                                    kind: 2,
-                                    intensity: l.intensity.get(),
-                                    unit_type: 0
-                                }
-                            ,
-                            AreaLight::Rectangle(ref l) => 
-                                LightTy {
-                                    kind: 3,
-                                    intensity: l.intensity.get(),
-                                    unit_type: 0
-                                }
-                            ,
-                            _ => LightTy {
-                                    kind: 0,
-                                    intensity: 0.0,
-                                    unit_type: 0
-                                }
-                        }
-                    } else {
-                        LightTy{
-                            kind: 0,
-                            intensity: 0.0,
-                            unit_type: 0
-                        }
-                    }
-            }
-        }).collect::<Vec<_>>();
-        let lights_ref = lights_cache.clone();
-
-        amethyst_imgui::with(|ui| {
-            Window::new(im_str!("Lighting Example"))
-                .size([300.0, 100.0], Condition::FirstUseEver)
-                .build(ui, || {
-                    ui.checkbox(im_str!("Free camera movement"), &mut state.free_camera_movement);
-                    // ui.label_text(im_str!("label"), im_str!("Value"));
-                    ComboBox::new(im_str!("Scene")).build_simple_string(ui,
-                        &mut state.scene,
-                        &[
-                            im_str!("Plane"),
-                            im_str!("Sponza"),
-                        ]);
-                    ui.separator();
-                    match state.scene {
-                        0 => {
-                            for mut light in &mut lights_cache {
-                                light_ui(ui, &mut light);
-                                ui.separator();
-                            }
-                        },
-                        1 => {
-                            for mut light in &mut lights_cache {
-                                light_ui(ui, &mut light);
-                                ui.separator();
-                            }
-                        }
-                        _ => ui.text(im_str!("Please select a scene!")),
-                    }
-                })
-        });
-        for (i, light) in lights_cache.iter().enumerate() {
-            if light.translation != lights_ref[i].translation {
-                transforms.get_mut(light.entity).unwrap().set_translation_xyz(light.translation[0], light.translation[1], light.translation[2]);
-            }
-            if light.rotation != lights_ref[i].rotation {
-                let ijk = Vector3::new(light.rotation[0], light.rotation[1], light.rotation[2]);
-                let quarternion = UnitQuaternion::from_quaternion(Quaternion::from_parts(light.rotation[3], ijk));
-                transforms.get_mut(light.entity).unwrap().set_rotation(quarternion);
-            }
-            if light.scale != lights_ref[i].scale {
-                let scale = Vector3::new(light.scale[0], light.scale[1], 1.0);
-                transforms.get_mut(light.entity).unwrap().set_scale(scale);
-            }
-            if light.light != lights_ref[i].light {
-                let new_light = match light.light.kind {
-                    1 => Some(Light::Area(AreaLight::Disk(light::area::Disk {
-                        intensity: light::area::Intensity::Power(light.light.intensity),
-                        two_sided: true,
-                        ..Default::default()
-                    }))),
-                    2 => Some(Light::Area(AreaLight::Sphere(light::area::Sphere {
-                        intensity: light::area::Intensity::Power(light.light.intensity),
-                        ..Default::default()
-                    }))),
-                    3 => Some(Light::Area(AreaLight::Rectangle(light::area::Rectangle {
-                        intensity: light::area::Intensity::Power(light.light.intensity),
-                        two_sided: true,
-                        ..Default::default()
-                    }))),
-                    _ => None
-                };
-                if let Some(l) = new_light {
-                    lights.insert(light.entity, l);
-                }
-            }
-        }
-    }
-}
-
-fn show_plane_ui(ui: &amethyst_imgui::imgui::Ui) {
-}
-
-fn light_ui(ui: &amethyst_imgui::imgui::Ui, light: &mut LightSync) {
-    use amethyst_imgui::imgui::*;
-    ui.tree_node(&im_str!("Light: {}", light.entity.id())).build(|| {
-        let translation = ui.push_id("translation");
-        // Translation
-        {
-                Slider::new(im_str!("Pos X"), -20.0..=20.0).build(ui, &mut light.translation[0]);
-                Slider::new(im_str!("Pos y"), -20.0..=20.0).build(ui, &mut light.translation[1]);
-                Slider::new(im_str!("Pos Z"), -20.0..=20.0).build(ui, &mut light.translation[2]);
-        }
-        translation.pop(ui);
-        let rot = ui.push_id("rot");
-        // Rotation
-        {
-            Slider::new(im_str!("X"), -1.0..=1.0).build(ui, &mut light.rotation[0]);
-            Slider::new(im_str!("Y"), -1.0..=1.0).build(ui, &mut light.rotation[1]);
-            Slider::new(im_str!("Z"), -1.0..=1.0).build(ui, &mut light.rotation[2]);
-            Slider::new(im_str!("W"), -1.0..=1.0).build(ui, &mut light.rotation[3]);
-        }
-        rot.pop(ui);
-        let scale = ui.push_id("scale");
-        // Rotation
-        {
-            Slider::new(im_str!("Scale X"), 0.0..=10.0).build(ui, &mut light.scale[0]);
-            Slider::new(im_str!("Scale Y"), 0.0..=10.0).build(ui, &mut light.scale[1]);
-        }
-        scale.pop(ui);
-        ui.separator();
-        ComboBox::new(im_str!("Scene")).build_simple_string(ui,
-            &mut light.light.kind,
-            &[
-                im_str!("N/A"),
-                im_str!("Disk"),
-                im_str!("Sphere"),
-                im_str!("Rect"),
-            ]);
-        ComboBox::new(im_str!("Light Unity")).build_simple_string(ui,
-            &mut light.light.unit_type,
-            &[
-                im_str!("Power"),
-                im_str!("Luminence"),
-            ]);
-        if light.light.unit_type == 0 {
-            Slider::new(im_str!("Power"), 0.0..=100.0).build(ui, &mut light.light.intensity);
-        } else if light.light.unit_type == 1 {
-            Slider::new(im_str!("Luminence"), 0.0..=100.0).build(ui, &mut light.light.intensity);
-        }
-    });
-}
\ No newline at end of file
@@ -0,0 +1,189 @@
+//! Procedural scene with a grid of spheres sweeping roughness and metalness.
+use amethyst::{
+    assets::{Handle, Prefab, PrefabLoader},
+    core::{
+        ecs::{Entities, Read, System, Write, WriteStorage},
+        math::Vector3,
+        Transform,
+    },
+    renderer::{
+        formats::{
+            mesh::MeshPrefab,
+            mtl::MaterialPrefab,
+            texture::{TextureGenerator, TexturePrefab},
+        },
+        light::{
+            area::{Intensity, Rectangle},
+            AreaLight, Light,
+        },
+        palette::Srgb,
+    },
+    Error,
+};
+use std::path::PathBuf;
+
+use crate::{
+    history::History,
+    prefab_data::{GenMeshVertex, Scene, ScenePrefabData},
+    scenes::{CameraPose, SceneInfo, SceneMap},
+    ui::UIState,
+};
+
+pub const MATERIAL_BALLS: &str = "material_balls";
+
+/// Distance between the centers of neighbouring spheres.
+const SPACING: f32 = 1.25;
+const SPHERE_RADIUS: f32 = 0.5;
+/// Height of the lowest row above the origin.
+const BASE_HEIGHT: f32 = 1.0;
+/// Mesh prefab shared by all spheres, scaled by their transforms.
+const SPHERE_MESH: &str = "Shape((shape: Sphere(32, 32)))";
+
+/// Layout of the generated grid, edited from the UI.
+#[derive(Clone, Debug)]
+pub struct MaterialBallSettings {
+    /// Roughness steps, from 0 on the left to 1 on the right.
+    pub columns: i32,
+    /// Metalness steps, from 0 at the bottom to 1 at the top.
+    pub rows: i32,
+    pub albedo: [f32; 3],
+    /// Set by the UI to rebuild the scene with the current settings.
+    pub regenerate: bool,
+}
+
+impl Default for MaterialBallSettings {
+    fn default() -> Self {
+        Self {
+            columns: 7,
+            rows: 4,
+            albedo: [0.8, 0.1, 0.1],
+            regenerate: false,
+        }
+    }
+}
+
+impl MaterialBallSettings {
+    fn center_height(&self) -> f32 {
+        BASE_HEIGHT + (self.rows.max(1) - 1) as f32 * SPACING / 2.0
+    }
+}
+
+/// Entry of the scene list; the grid itself comes from `build_prefab`.
+pub fn scene_info(settings: &MaterialBallSettings) -> SceneInfo {
+    let height = settings.center_height();
+    SceneInfo {
+        name: MATERIAL_BALLS.into(),
+        asset_path: String::new(),
+        file_path: PathBuf::new(),
+        display_name: "Material balls".into(),
+        description: "Spheres with roughness increasing to the right and metalness increasing upwards, lit by a \
+                      rectangle light."
+            .into(),
+        camera: CameraPose {
+            position: [0.0, height, 2.0 + SPACING * settings.columns.max(settings.rows) as f32],
+            target: [0.0, height, 0.0],
+            fov: 60.0,
+        },
+        procedural: true,
+    }
+}
+
+fn generate(r: f32, g: f32, b: f32, a: f32) -> Option<TexturePrefab> {
+    Some(TexturePrefab::Generate(TextureGenerator::LinearRgba(r, g, b, a)))
+}
+
+/// Steps `index` of `count` evenly from 0 to 1.
+fn sweep(index: i32, count: i32) -> f32 {
+    if count > 1 {
+        index as f32 / (count - 1) as f32
+    } else {
+        0.5
+    }
+}
+
+pub fn build_prefab(settings: &MaterialBallSettings) -> Result<Prefab<ScenePrefabData>, Error> {
+    let mut prefab = Prefab::new();
+    let mesh = ron::de::from_str::<MeshPrefab<GenMeshVertex>>(SPHERE_MESH)?;
+    let columns = settings.columns.max(1);
+    let rows = settings.rows.max(1);
+    let [red, green, blue] = settings.albedo;
+    let left = -(columns - 1) as f32 * SPACING / 2.0;
+
+    for row in 0..rows {
+        for column in 0..columns {
+            let mut transform = Transform::default();
+            transform.set_translation_xyz(left + column as f32 * SPACING, BASE_HEIGHT + row as f32 * SPACING, 0.0);
+            transform.set_scale(Vector3::new(SPHERE_RADIUS, SPHERE_RADIUS, SPHERE_RADIUS));
+
+            let mut material = MaterialPrefab::default();
+            material.albedo = generate(red, green, blue, 1.0);
+            // Roughness is read from the green and metalness from the blue channel.
+            material.metallic_roughness = generate(0.0, sweep(column, columns), sweep(row, rows), 1.0);
+            prefab.add(Some(0), Some(ScenePrefabData::with_mesh(transform, mesh.clone(), material)));
+        }
+    }
+
+    // A rectangle light in front of the grid, emitting towards it along -Z.
+    let mut transform = Transform::default();
+    transform.set_translation_xyz(0.0, settings.center_height(), 3.0);
+    transform.set_scale(Vector3::new(columns as f32 * SPACING / 2.0, 0.5, 1.0));
+    let light = Light::Area(AreaLight::Rectangle(Rectangle {
+        intensity: Intensity::Power(20.0),
+        diffuse_color: Srgb::new(1.0, 1.0, 1.0),
+        spec_color: Srgb::new(1.0, 1.0, 1.0),
+        two_sided: false,
+        ..Default::default()
+    }));
+    prefab.add(Some(0), Some(ScenePrefabData::with_light(transform, light)));
+    Ok(prefab)
+}
+
+/// Rebuilds the material ball scene when `MaterialBallSettings::regenerate` is set.
+///
+/// The camera stays where it is, unlike when switching scenes.
+#[derive(Default, Debug)]
+pub struct MaterialBallSystem;
+
+impl<'a> System<'a> for MaterialBallSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Write<'a, MaterialBallSettings>,
+        Write<'a, SceneMap>,
+        Write<'a, Scene>,
+        Write<'a, History>,
+        PrefabLoader<'a, ScenePrefabData>,
+        Entities<'a>,
+        WriteStorage<'a, Handle<Prefab<ScenePrefabData>>>,
+        Read<'a, UIState>,
+    );
+
+    fn run(
+        &mut self,
+        (mut settings, mut scene_map, mut scene, mut history, loader, entities, mut prefabs, state): Self::SystemData,
+    ) {
+        if !settings.regenerate {
+            return;
+        }
+        settings.regenerate = false;
+        let index = match scene_map.index_of(MATERIAL_BALLS) {
+            Some(index) => index,
+            None => return,
+        };
+        let handle = match build_prefab(&settings) {
+            Ok(prefab) => loader.load_from_data(prefab, ()),
+            Err(e) => {
+                println!("Failed to build the material balls: {}", e);
+                return;
+            }
+        };
+        scene_map.set_handle(index, handle.clone());
+
+        if scene.scene == Some(index) && state.scene == index {
+            if let Some(root) = scene.entity {
+                let _ = entities.delete(root);
+            }
+            scene.entity = Some(entities.build_entity().with(handle, &mut prefabs).build());
+            history.clear();
+        }
+    }
+}
@@ -0,0 +1,429 @@
+//! Live editing of `Material` assets.
+//!
+//! Amethyst materials have no scalar factors, every parameter is a texture. Editing a factor
+//! generates a 1×1 texture of that value and swaps it into a copy of the material, which then
+//! replaces the original on every entity sharing it.
+use amethyst::{
+    assets::{AssetLoaderSystemData, AssetStorage, Handle, Loader},
+    core::{
+        ecs::{Entities, Entity, Join, Read, ReadExpect, ReadStorage, System, Write, WriteStorage},
+        Parent,
+    },
+    renderer::{palette::LinSrgba, rendy::texture::palette::load_from_linear_rgba, types::Texture, Material},
+};
+use amethyst_imgui::imgui::{self, ImStr, ImString};
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    history::{Edit, History},
+    light_proxy::LightProxy,
+    prefab_data::Scene,
+    scenes,
+};
+
+const DEFAULT_ALBEDO: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const DEFAULT_EMISSION: [f32; 3] = [0.0, 0.0, 0.0];
+/// Metallic and roughness.
+const DEFAULT_METALLIC_ROUGHNESS: [f32; 2] = [0.0, 0.5];
+
+/// Solid values applied to the texture slots of a material.
+///
+/// `None` while a slot still holds a texture, whose values are on the GPU only.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Factors {
+    pub albedo: Option<[f32; 4]>,
+    pub emission: Option<[f32; 3]>,
+    /// Metallic and roughness, which share a texture.
+    pub metallic_roughness: Option<[f32; 2]>,
+    pub alpha_cutoff: f32,
+}
+
+impl Factors {
+    /// Factors of a material that was not edited yet; only the alpha cutoff is known.
+    pub fn new(material: &Material) -> Self {
+        Self {
+            albedo: None,
+            emission: None,
+            metallic_roughness: None,
+            alpha_cutoff: material.alpha_cutoff,
+        }
+    }
+
+    /// Factors after a texture or, with `solid`, a 1×1 texture of the factor was put into `slot`.
+    fn with_slot(mut self, slot: usize, solid: bool) -> Self {
+        match slot {
+            0 => self.albedo = if solid { Some(self.albedo.unwrap_or(DEFAULT_ALBEDO)) } else { None },
+            1 => self.emission = if solid { Some(self.emission.unwrap_or(DEFAULT_EMISSION)) } else { None },
+            3 => {
+                self.metallic_roughness = if solid {
+                    Some(self.metallic_roughness.unwrap_or(DEFAULT_METALLIC_ROUGHNESS))
+                } else {
+                    None
+                }
+            }
+            _ => {}
+        }
+        self
+    }
+}
+
+/// Edited copy of a material that is still being loaded.
+#[derive(Debug)]
+struct PendingMaterial {
+    handle: Handle<Material>,
+    material: Material,
+    factors: Factors,
+}
+
+/// Factors last applied to each edited material, and edited materials waiting to be loaded.
+///
+/// Keyed by the handle itself rather than its id, since ids are reused once a material is dropped.
+#[derive(Default, Debug)]
+pub struct MaterialFactors {
+    factors: HashMap<Handle<Material>, Factors>,
+    /// Edits by the material they replace. Entities keep the old material until the new one is
+    /// loaded, so they never render without one.
+    pending: HashMap<Handle<Material>, PendingMaterial>,
+    /// Root of the scene the factors belong to.
+    scene: Option<Entity>,
+}
+
+impl MaterialFactors {
+    pub fn get(&self, handle: &Handle<Material>, material: &Material) -> Factors {
+        match self.pending.get(handle) {
+            Some(pending) => pending.factors,
+            None => self
+                .factors
+                .get(handle)
+                .cloned()
+                .unwrap_or_else(|| Factors::new(material)),
+        }
+    }
+
+    /// `material` including an edit that is still loading, to base further edits on.
+    pub fn latest(&self, handle: &Handle<Material>, material: &Material) -> Material {
+        self.pending
+            .get(handle)
+            .map_or_else(|| material.clone(), |pending| pending.material.clone())
+    }
+
+    /// Loads `material` to replace `old` on every entity using it once it is available.
+    ///
+    /// A later edit of `old` before then supersedes this one.
+    pub fn replace(
+        &mut self,
+        old: &Handle<Material>,
+        material: Material,
+        factors: Factors,
+        loader: &Loader,
+        storage: &AssetStorage<Material>,
+    ) {
+        let handle = loader.load_from_data(material.clone(), (), storage);
+        self.pending.insert(
+            old.clone(),
+            PendingMaterial {
+                handle,
+                material,
+                factors,
+            },
+        );
+    }
+
+    /// Puts back the factors of a material brought back by undo or redo.
+    pub fn restore(&mut self, handle: &Handle<Material>, factors: Option<Factors>) {
+        match factors {
+            Some(factors) => {
+                self.factors.insert(handle.clone(), factors);
+            }
+            None => {
+                self.factors.remove(handle);
+            }
+        }
+    }
+
+    /// Assigns the edited materials that finished loading and records them in the `History`,
+    /// forgets all factors when the scene changes and those of materials no entity uses anymore.
+    pub fn update(
+        &mut self,
+        scene: Option<Entity>,
+        storage: &AssetStorage<Material>,
+        entities: &Entities<'_>,
+        handles: &mut WriteStorage<'_, Handle<Material>>,
+        history: &mut History,
+    ) {
+        if self.scene != scene {
+            self.scene = scene;
+            self.factors.clear();
+            self.pending.clear();
+        }
+        let loaded = self
+            .pending
+            .iter()
+            .filter(|(_, pending)| storage.get(&pending.handle).is_some())
+            .map(|(old, _)| old.clone())
+            .collect::<Vec<_>>();
+        for old in loaded {
+            let pending = self.pending.remove(&old).unwrap();
+            let before = (old.clone(), self.factors.get(&old).cloned());
+            let after = (pending.handle.clone(), Some(pending.factors));
+            let mut edits = Vec::new();
+            for (entity, handle) in (&**entities, &mut *handles).join() {
+                if *handle == old {
+                    *handle = pending.handle.clone();
+                    edits.push(Edit::material(entity, before.clone(), after.clone()));
+                }
+            }
+            // Not labelled with the material, which changes with every edit of a drag.
+            history.record("Edit material".into(), edits);
+            self.factors.insert(pending.handle, pending.factors);
+        }
+        if !self.factors.is_empty() {
+            let used = handles.join().collect::<HashSet<_>>();
+            self.factors.retain(|handle, _| used.contains(handle));
+        }
+    }
+}
+
+/// Shows that `label` comes from a texture, returns whether it should be replaced by a factor.
+fn texture_slot_ui(ui: &imgui::Ui, label: &str) -> bool {
+    use amethyst_imgui::imgui::*;
+    ui.text(&im_str!("{}: texture", label));
+    ui.same_line(0.0);
+    ui.small_button(&im_str!("Use factor##{}", label))
+}
+
+/// Draws the factor controls, returns whether any changed.
+pub fn factors_ui(ui: &imgui::Ui, factors: &mut Factors) -> bool {
+    use amethyst_imgui::imgui::*;
+    let mut changed = false;
+    if let Some(ref mut albedo) = factors.albedo {
+        changed |= ColorEdit::new(im_str!("Albedo"), albedo).build(ui);
+    } else if texture_slot_ui(ui, "Albedo") {
+        factors.albedo = Some(DEFAULT_ALBEDO);
+        changed = true;
+    }
+    if let Some(ref mut emission) = factors.emission {
+        changed |= ColorEdit::new(im_str!("Emission"), emission).build(ui);
+    } else if texture_slot_ui(ui, "Emission") {
+        factors.emission = Some(DEFAULT_EMISSION);
+        changed = true;
+    }
+    if let Some([ref mut metallic, ref mut roughness]) = factors.metallic_roughness {
+        changed |= Slider::new(im_str!("Metallic"), 0.0..=1.0).build(ui, metallic);
+        changed |= Slider::new(im_str!("Roughness"), 0.0..=1.0).build(ui, roughness);
+    } else if texture_slot_ui(ui, "Metallic/roughness") {
+        factors.metallic_roughness = Some(DEFAULT_METALLIC_ROUGHNESS);
+        changed = true;
+    }
+    changed |= Slider::new(im_str!("Alpha cutoff"), 0.0..=1.0).build(ui, &mut factors.alpha_cutoff);
+    ui.text_disabled(im_str!("Using a factor replaces its texture."));
+    changed
+}
+
+pub fn solid_texture(textures: &AssetLoaderSystemData<'_, Texture>, color: LinSrgba) -> Handle<Texture> {
+    textures.load_from_data(load_from_linear_rgba(color).into(), ())
+}
+
+/// Copy of `material` with the slots whose factor differs between `before` and `after` replaced.
+pub fn apply_factors(
+    material: &Material,
+    before: &Factors,
+    after: &Factors,
+    textures: &AssetLoaderSystemData<'_, Texture>,
+) -> Material {
+    let mut material = material.clone();
+    if before.albedo != after.albedo {
+        if let Some([r, g, b, a]) = after.albedo {
+            material.albedo = solid_texture(textures, LinSrgba::new(r, g, b, a));
+        }
+    }
+    if before.emission != after.emission {
+        if let Some([r, g, b]) = after.emission {
+            material.emission = solid_texture(textures, LinSrgba::new(r, g, b, 1.0));
+        }
+    }
+    if before.metallic_roughness != after.metallic_roughness {
+        if let Some([metallic, roughness]) = after.metallic_roughness {
+            // Roughness is read from the green and metalness from the blue channel.
+            material.metallic_roughness = solid_texture(textures, LinSrgba::new(0.0, roughness, metallic, 1.0));
+        }
+    }
+    material.alpha_cutoff = after.alpha_cutoff;
+    material
+}
+
+/// Number of entities using `material`.
+pub fn users(material: &Handle<Material>, handles: &WriteStorage<'_, Handle<Material>>) -> usize {
+    handles.join().filter(|handle| *handle == material).count()
+}
+
+pub const TEXTURE_SLOTS: [&str; 6] = ["Albedo", "Emission", "Normal", "Metallic/roughness", "Ambient occlusion", "Cavity"];
+
+fn slot_mut(material: &mut Material, slot: usize) -> &mut Handle<Texture> {
+    match slot {
+        0 => &mut material.albedo,
+        1 => &mut material.emission,
+        2 => &mut material.normal,
+        3 => &mut material.metallic_roughness,
+        4 => &mut material.ambient_occlusion,
+        _ => &mut material.cavity,
+    }
+}
+
+fn slot(material: &Material, slot: usize) -> &Handle<Texture> {
+    match slot {
+        0 => &material.albedo,
+        1 => &material.emission,
+        2 => &material.normal,
+        3 => &material.metallic_roughness,
+        4 => &material.ambient_occlusion,
+        _ => &material.cavity,
+    }
+}
+
+/// Neutral 1×1 texture for a slot; the factor slots use the current factors.
+fn solid_slot_texture(slot: usize, factors: &Factors, textures: &AssetLoaderSystemData<'_, Texture>) -> Handle<Texture> {
+    let color = match slot {
+        0 => {
+            let [r, g, b, a] = factors.albedo.unwrap_or(DEFAULT_ALBEDO);
+            LinSrgba::new(r, g, b, a)
+        }
+        1 => {
+            let [r, g, b] = factors.emission.unwrap_or(DEFAULT_EMISSION);
+            LinSrgba::new(r, g, b, 1.0)
+        }
+        // Tangent space normal pointing straight out of the surface.
+        2 => LinSrgba::new(0.5, 0.5, 1.0, 1.0),
+        3 => {
+            let [metallic, roughness] = factors.metallic_roughness.unwrap_or(DEFAULT_METALLIC_ROUGHNESS);
+            LinSrgba::new(0.0, roughness, metallic, 1.0)
+        }
+        _ => LinSrgba::new(1.0, 1.0, 1.0, 1.0),
+    };
+    solid_texture(textures, color)
+}
+
+#[derive(Clone, Copy, Debug)]
+enum MaterialEdit {
+    Factors(Factors),
+    /// Puts the texture at the given index of the browser's texture list into a slot, `None` for a solid texture.
+    Slot(usize, Option<usize>),
+}
+
+/// Lists every material used by the entities of the loaded scene, including the ones spawned by
+/// gltf scenes, and edits their factors and texture slots.
+///
+/// Edits replace the material on all entities that share it.
+#[derive(Default, Debug)]
+pub struct MaterialBrowserSystem;
+
+impl<'a> System<'a> for MaterialBrowserSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Read<'a, Scene>,
+        Write<'a, MaterialFactors>,
+        Write<'a, History>,
+        Entities<'a>,
+        ReadStorage<'a, Parent>,
+        ReadStorage<'a, LightProxy>,
+        WriteStorage<'a, Handle<Material>>,
+        Read<'a, AssetStorage<Material>>,
+        ReadExpect<'a, Loader>,
+        AssetLoaderSystemData<'a, Texture>,
+    );
+
+    fn run(
+        &mut self,
+        (scene, mut factors, mut history, entities, parents, proxies, mut handles, storage, loader, textures): Self::SystemData,
+    ) {
+        use amethyst_imgui::imgui::*;
+
+        factors.update(scene.entity, &storage, &entities, &mut handles, &mut history);
+        let root = match scene.entity {
+            Some(root) => root,
+            None => return,
+        };
+
+        let mut users = HashMap::<u32, (Handle<Material>, usize)>::new();
+        for (entity, handle, _) in (&entities, &handles, !&proxies).join() {
+            if scenes::is_descendant(entity, root, &parents) {
+                users.entry(handle.id()).or_insert_with(|| (handle.clone(), 0)).1 += 1;
+            }
+        }
+        let mut scene_materials = users
+            .into_iter()
+            .filter_map(|(_, (handle, users))| {
+                let material = storage.get(&handle)?.clone();
+                Some((handle, material, users))
+            })
+            .collect::<Vec<_>>();
+        scene_materials.sort_by_key(|(handle, _, _)| handle.id());
+
+        let mut scene_textures = Vec::<Handle<Texture>>::new();
+        for (_, material, _) in &scene_materials {
+            for index in 0..TEXTURE_SLOTS.len() {
+                let texture = slot(material, index);
+                if !scene_textures.contains(texture) {
+                    scene_textures.push(texture.clone());
+                }
+            }
+        }
+        scene_textures.sort_by_key(|texture| texture.id());
+        let mut texture_names = vec![ImString::new("Solid")];
+        texture_names.extend(scene_textures.iter().map(|texture| im_str!("Texture {}", texture.id())));
+        let texture_names = texture_names.iter().map(|name| name.as_ref()).collect::<Vec<&ImStr>>();
+
+        let mut edits = Vec::new();
+        amethyst_imgui::with(|ui| {
+            Window::new(im_str!("Materials"))
+                .size([320.0, 400.0], Condition::FirstUseEver)
+                .build(ui, || {
+                    ui.text_disabled(&im_str!("{} materials, {} textures", scene_materials.len(), scene_textures.len()));
+                    for (index, (handle, material, users)) in scene_materials.iter().enumerate() {
+                        let id = handle.id();
+                        ui.tree_node(&im_str!("Material {} ({} entities)###material{}", id, users, id))
+                            .build(|| {
+                                let imgui_id = ui.push_id(id as i32);
+                                let mut material_factors = factors.get(handle, material);
+                                if factors_ui(ui, &mut material_factors) {
+                                    edits.push((index, MaterialEdit::Factors(material_factors)));
+                                }
+                                for (slot_index, slot_name) in TEXTURE_SLOTS.iter().enumerate() {
+                                    let current = slot(material, slot_index);
+                                    let mut selected = scene_textures
+                                        .iter()
+                                        .position(|texture| texture == current)
+                                        .map_or(0, |position| position + 1);
+                                    if ComboBox::new(&ImString::new(*slot_name)).build_simple_string(
+                                        ui,
+                                        &mut selected,
+                                        &texture_names,
+                                    ) {
+                                        edits.push((index, MaterialEdit::Slot(slot_index, selected.checked_sub(1))));
+                                    }
+                                }
+                                imgui_id.pop(ui);
+                            });
+                    }
+                });
+        });
+
+        for (index, edit) in edits {
+            let (ref handle, ref material, _) = scene_materials[index];
+            let before = factors.get(handle, material);
+            let material = &factors.latest(handle, material);
+            let (edited, after) = match edit {
+                MaterialEdit::Factors(after) => (apply_factors(material, &before, &after, &textures), after),
+                MaterialEdit::Slot(slot_index, texture) => {
+                    let mut edited = material.clone();
+                    *slot_mut(&mut edited, slot_index) = match texture {
+                        Some(texture) => scene_textures[texture].clone(),
+                        None => solid_slot_texture(slot_index, &before, &textures),
+                    };
+                    (edited, before.with_slot(slot_index, texture.is_none()))
+                }
+            };
+            factors.replace(handle, edited, after, &loader, &storage);
+        }
+    }
+}
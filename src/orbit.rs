@@ -0,0 +1,249 @@
+//! Turntable camera rotating around a focus point.
+use amethyst::{
+    controls::FlyControlTag,
+    core::{
+        ecs::{Entities, Entity, Join, Read, ReadStorage, System, SystemData, World, Write, WriteStorage},
+        math::{Point3, UnitQuaternion, Vector3},
+        shrev::{EventChannel, ReaderId},
+        Parent, SystemDesc, Transform,
+    },
+    input::{InputEvent, ScrollDirection},
+    renderer::{light::Light, visibility::BoundingSphere, ActiveCamera, Camera},
+    winit::{MouseButton, VirtualKeyCode},
+};
+
+use crate::{filtered_input::FilteredInputEvent, gizmo::Gizmo, picking, scenes, ui::UIState};
+
+pub const FLY: usize = 0;
+pub const ORBIT: usize = 1;
+
+pub const CAMERA_MODES: [&str; 2] = ["Fly", "Orbit"];
+
+/// Degrees of rotation per pixel of mouse travel.
+const ROTATE_SPEED: f32 = 0.3;
+/// Pan distance per pixel, relative to the distance to the focus point.
+const PAN_SPEED: f32 = 0.002;
+/// Distance factor per wheel step towards the focus point.
+const DOLLY_STEP: f32 = 0.9;
+const MIN_DISTANCE: f32 = 0.01;
+/// Keeps the camera off the poles, where looking at the focus point has no defined up direction.
+const MAX_PITCH: f32 = 89.0;
+/// Extra room around the selection when framing it.
+const FRAME_MARGIN: f32 = 1.2;
+/// Radius framed for entities without any bounds.
+const DEFAULT_RADIUS: f32 = 0.5;
+
+/// Orbits the active camera around a focus point while `UIState::camera_mode` is `ORBIT`.
+///
+/// Left drags rotate, middle drags pan and the wheel dollies; input taken by imgui is ignored.
+/// The fly controls are detached from the camera while orbiting. F or `UIState::frame_selected`
+/// moves the camera back until the selection fills the view, in either mode.
+pub struct OrbitCameraSystem {
+    event_reader: ReaderId<FilteredInputEvent>,
+    focus: Point3<f32>,
+    distance: f32,
+    /// Camera placement written last, any other placement means the camera was moved elsewhere.
+    placed: Option<(Vector3<f32>, UnitQuaternion<f32>)>,
+    rotating: bool,
+    panning: bool,
+}
+
+impl<'a> System<'a> for OrbitCameraSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Read<'a, EventChannel<FilteredInputEvent>>,
+        Write<'a, UIState>,
+        Read<'a, Gizmo>,
+        Read<'a, ActiveCamera>,
+        Entities<'a>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, FlyControlTag>,
+        ReadStorage<'a, Camera>,
+        ReadStorage<'a, Light>,
+        ReadStorage<'a, BoundingSphere>,
+        ReadStorage<'a, Parent>,
+    );
+
+    fn run(
+        &mut self,
+        (events, mut state, gizmo, camera, entities, mut transforms, mut tags, cameras, lights, bounds, parents): Self::SystemData,
+    ) {
+        let orbit = state.camera_mode == ORBIT;
+        let mut rotate = (0.0, 0.0);
+        let mut pan = (0.0, 0.0);
+        let mut dolly = 0;
+        for event in events.read(&mut self.event_reader) {
+            match *event {
+                FilteredInputEvent::Free(InputEvent::MouseButtonPressed(MouseButton::Left)) => {
+                    self.rotating = !gizmo.dragging;
+                }
+                FilteredInputEvent::Free(InputEvent::MouseButtonPressed(MouseButton::Middle)) => self.panning = true,
+                FilteredInputEvent::Free(InputEvent::MouseButtonReleased(MouseButton::Left))
+                | FilteredInputEvent::Filtered(InputEvent::MouseButtonReleased(MouseButton::Left)) => {
+                    self.rotating = false
+                }
+                FilteredInputEvent::Free(InputEvent::MouseButtonReleased(MouseButton::Middle))
+                | FilteredInputEvent::Filtered(InputEvent::MouseButtonReleased(MouseButton::Middle)) => {
+                    self.panning = false
+                }
+                FilteredInputEvent::Free(InputEvent::MouseMoved { delta_x, delta_y }) => {
+                    if self.rotating && !gizmo.dragging {
+                        rotate.0 += delta_x as f32;
+                        rotate.1 += delta_y as f32;
+                    }
+                    if self.panning {
+                        pan.0 += delta_x as f32;
+                        pan.1 += delta_y as f32;
+                    }
+                }
+                FilteredInputEvent::Free(InputEvent::MouseWheelMoved(direction)) => match direction {
+                    ScrollDirection::ScrollUp => dolly += 1,
+                    ScrollDirection::ScrollDown => dolly -= 1,
+                    _ => {}
+                },
+                FilteredInputEvent::Free(InputEvent::KeyPressed {
+                    key_code: VirtualKeyCode::F,
+                    ..
+                }) => state.frame_selected = true,
+                _ => {}
+            }
+        }
+
+        let camera = match camera.entity {
+            Some(camera) => camera,
+            None => return,
+        };
+        if orbit {
+            tags.remove(camera);
+        } else if !tags.contains(camera) {
+            let _ = tags.insert(camera, FlyControlTag);
+        }
+
+        let framed = if state.frame_selected {
+            state.frame_selected = false;
+            state
+                .selected
+                .filter(|selected| entities.is_alive(*selected))
+                .map(|selected| selection_bounds(selected, &entities, &transforms, &lights, &bounds, &parents))
+        } else {
+            None
+        };
+
+        let transform = match transforms.get_mut(camera) {
+            Some(transform) => transform,
+            None => return,
+        };
+        let position = *transform.translation();
+        let rotation = *transform.rotation();
+        // Direction from the focus point to the camera.
+        let back = rotation * Vector3::z();
+        if self.placed != Some((position, rotation)) {
+            self.focus = Point3::from(position - back * self.distance);
+        }
+
+        let mut moved = false;
+        if let Some((center, radius)) = framed {
+            let fovy = cameras
+                .get(camera)
+                .and_then(|camera| camera.projection().as_perspective().map(|p| p.fovy()));
+            self.focus = center;
+            self.distance = match fovy {
+                Some(fovy) => radius * FRAME_MARGIN / (fovy / 2.0).sin(),
+                None => radius * 3.0,
+            }
+            .max(MIN_DISTANCE);
+            moved = true;
+        }
+
+        let mut pitch = back.y.max(-1.0).min(1.0).asin();
+        let mut yaw = back.x.atan2(back.z);
+        if orbit {
+            if rotate != (0.0, 0.0) {
+                yaw -= (rotate.0 * ROTATE_SPEED).to_radians();
+                pitch += (rotate.1 * ROTATE_SPEED).to_radians();
+                moved = true;
+            }
+            if pan != (0.0, 0.0) {
+                let right = rotation * Vector3::x();
+                let up = rotation * Vector3::y();
+                self.focus += (up * pan.1 - right * pan.0) * self.distance * PAN_SPEED;
+                moved = true;
+            }
+            if dolly != 0 {
+                self.distance = (self.distance * DOLLY_STEP.powi(dolly)).max(MIN_DISTANCE);
+                moved = true;
+            }
+        }
+
+        if moved {
+            let pitch = pitch.max(-MAX_PITCH.to_radians()).min(MAX_PITCH.to_radians());
+            let back = Vector3::new(pitch.cos() * yaw.sin(), pitch.sin(), pitch.cos() * yaw.cos());
+            transform.set_translation(self.focus.coords + back * self.distance);
+            transform.face_towards(self.focus.coords, Vector3::y());
+        }
+        self.placed = Some((*transform.translation(), *transform.rotation()));
+    }
+}
+
+/// World space sphere enclosing `selected` and everything below it in the hierarchy.
+fn selection_bounds(
+    selected: Entity,
+    entities: &Entities<'_>,
+    transforms: &WriteStorage<'_, Transform>,
+    lights: &ReadStorage<'_, Light>,
+    bounds: &ReadStorage<'_, BoundingSphere>,
+    parents: &ReadStorage<'_, Parent>,
+) -> (Point3<f32>, f32) {
+    let mut sphere: Option<(Point3<f32>, f32)> = None;
+    for (entity, transform, light, bound) in (entities, transforms, lights.maybe(), bounds.maybe()).join() {
+        if entity != selected && !scenes::is_descendant(entity, selected, parents) {
+            continue;
+        }
+        let part = match (light, bound) {
+            (Some(light), _) => picking::light_bounds(transform, light),
+            (None, Some(bound)) => picking::mesh_bounds(transform, bound),
+            (None, None) => continue,
+        };
+        sphere = Some(match sphere {
+            Some(sphere) => merge_spheres(sphere, part),
+            None => part,
+        });
+    }
+    sphere.unwrap_or_else(|| {
+        let center = transforms
+            .get(selected)
+            .map_or_else(Point3::origin, |transform| transform.global_matrix().transform_point(&Point3::origin()));
+        (center, DEFAULT_RADIUS)
+    })
+}
+
+fn merge_spheres((a, a_radius): (Point3<f32>, f32), (b, b_radius): (Point3<f32>, f32)) -> (Point3<f32>, f32) {
+    let distance = (b - a).norm();
+    if distance + b_radius <= a_radius {
+        (a, a_radius)
+    } else if distance + a_radius <= b_radius {
+        (b, b_radius)
+    } else {
+        let radius = (distance + a_radius + b_radius) / 2.0;
+        (a + (b - a) * ((radius - a_radius) / distance), radius)
+    }
+}
+
+#[derive(Default)]
+pub struct OrbitCameraSystemDesc;
+impl<'a, 'b> SystemDesc<'a, 'b, OrbitCameraSystem> for OrbitCameraSystemDesc {
+    fn build(self, world: &mut World) -> OrbitCameraSystem {
+        <OrbitCameraSystem as System<'_>>::SystemData::setup(world);
+
+        let event_reader = Write::<EventChannel<FilteredInputEvent>>::fetch(world).register_reader();
+
+        OrbitCameraSystem {
+            event_reader,
+            focus: Point3::origin(),
+            distance: 5.0,
+            placed: None,
+            rotating: false,
+            panning: false,
+        }
+    }
+}
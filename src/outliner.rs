@@ -0,0 +1,325 @@
+//! Tree view of the entities of the loaded scene.
+use amethyst::{
+    assets::Handle,
+    core::{
+        ecs::{Entities, Entity, Read, ReadExpect, ReadStorage, System, Write, WriteStorage},
+        math::{Matrix3, Matrix4, Rotation3, UnitQuaternion, Vector3},
+        HiddenPropagate, Named, Parent, ParentHierarchy, Transform,
+    },
+    renderer::{light::Light, types::Mesh, Camera, Material},
+};
+use amethyst_imgui::imgui::ImString;
+use std::collections::HashSet;
+
+use crate::{
+    history::{Edit, History},
+    light_proxy::LightProxy,
+    prefab_data::Scene,
+    scenes,
+    ui::UIState,
+};
+
+/// A visible line of the outliner.
+struct Row {
+    entity: Entity,
+    depth: usize,
+    has_children: bool,
+    label: ImString,
+    badges: ImString,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum OutlinerAction {
+    Toggle(Entity),
+    Click(Entity),
+    Rename,
+    SetHidden(bool),
+    Reparent(Entity),
+}
+
+/// Lists the `Parent` hierarchy under `Scene.entity`, including the children spawned by gltf
+/// scenes, and edits the name, visibility and parent of the selected entity.
+///
+/// Hiding uses `HiddenPropagate`, which affects meshes only; lights keep shining.
+pub struct OutlinerSystem {
+    expanded: HashSet<Entity>,
+    name: ImString,
+    last_selected: Option<Entity>,
+    /// Whether the next clicked row becomes the new parent of the selection.
+    picking_parent: bool,
+}
+
+impl Default for OutlinerSystem {
+    fn default() -> Self {
+        Self {
+            expanded: HashSet::new(),
+            name: ImString::with_capacity(64),
+            last_selected: None,
+            picking_parent: false,
+        }
+    }
+}
+
+impl<'a> System<'a> for OutlinerSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Write<'a, UIState>,
+        Read<'a, Scene>,
+        ReadExpect<'a, ParentHierarchy>,
+        Write<'a, History>,
+        Entities<'a>,
+        WriteStorage<'a, Named>,
+        WriteStorage<'a, Parent>,
+        WriteStorage<'a, Transform>,
+        WriteStorage<'a, HiddenPropagate>,
+        ReadStorage<'a, Handle<Mesh>>,
+        ReadStorage<'a, Handle<Material>>,
+        ReadStorage<'a, Light>,
+        ReadStorage<'a, Camera>,
+        ReadStorage<'a, LightProxy>,
+    );
+
+    fn run(
+        &mut self,
+        (
+            mut state,
+            scene,
+            hierarchy,
+            mut history,
+            entities,
+            mut names,
+            mut parents,
+            mut transforms,
+            mut hidden,
+            meshes,
+            materials,
+            lights,
+            cameras,
+            proxies,
+        ): Self::SystemData,
+    ) {
+        use amethyst_imgui::imgui::*;
+
+        let root = match scene.entity {
+            Some(root) => root,
+            None => return,
+        };
+        self.expanded.retain(|entity| entities.is_alive(*entity));
+        let selected = state.selected.filter(|entity| entities.is_alive(*entity));
+        if selected != self.last_selected {
+            self.last_selected = selected;
+            self.picking_parent = false;
+            self.name.clear();
+            if let Some(name) = selected.and_then(|entity| names.get(entity)) {
+                self.name.push_str(&name.name);
+            }
+        }
+
+        let mut rows = Vec::new();
+        let mut stack = hierarchy.children(root).iter().rev().map(|child| (*child, 0)).collect::<Vec<_>>();
+        while let Some((entity, depth)) = stack.pop() {
+            // Light surfaces are an editor aid, not part of the scene.
+            if proxies.contains(entity) {
+                continue;
+            }
+            let children = hierarchy.children(entity);
+            let mut badges = Vec::new();
+            if meshes.contains(entity) {
+                badges.push("mesh");
+            }
+            if materials.contains(entity) {
+                badges.push("material");
+            }
+            if lights.contains(entity) {
+                badges.push("light");
+            }
+            if cameras.contains(entity) {
+                badges.push("camera");
+            }
+            if hidden.contains(entity) {
+                badges.push("hidden");
+            }
+            rows.push(Row {
+                entity,
+                depth,
+                has_children: !children.is_empty(),
+                label: im_str!("{}##outliner{}", display_name(entity, &names), entity.id()),
+                badges: ImString::new(badges.iter().map(|badge| format!("[{}]", badge)).collect::<Vec<_>>().join(" ")),
+            });
+            if self.expanded.contains(&entity) {
+                stack.extend(children.iter().rev().map(|child| (*child, depth + 1)));
+            }
+        }
+
+        let selected_name = selected.map(|entity| ImString::new(display_name(entity, &names)));
+        let mut is_hidden = selected.map_or(false, |entity| hidden.contains(entity));
+        let mut actions = Vec::new();
+        amethyst_imgui::with(|ui| {
+            Window::new(im_str!("Outliner"))
+                .size([300.0, 400.0], Condition::FirstUseEver)
+                .build(ui, || {
+                    if let Some(ref selected_name) = selected_name {
+                        ui.text(selected_name);
+                        if ui
+                            .input_text(im_str!("Name"), &mut self.name)
+                            .enter_returns_true(true)
+                            .build()
+                        {
+                            actions.push(OutlinerAction::Rename);
+                        }
+                        if ui.checkbox(im_str!("Hidden"), &mut is_hidden) {
+                            actions.push(OutlinerAction::SetHidden(is_hidden));
+                        }
+                        if ui.small_button(im_str!("Set parent...")) {
+                            self.picking_parent = !self.picking_parent;
+                        }
+                        ui.same_line(0.0);
+                        if ui.small_button(im_str!("Move to scene root")) {
+                            actions.push(OutlinerAction::Reparent(root));
+                        }
+                        if self.picking_parent {
+                            ui.text_disabled(im_str!("Click the new parent below."));
+                        }
+                    } else {
+                        ui.text_disabled(im_str!("Nothing selected."));
+                    }
+                    ui.separator();
+
+                    for row in &rows {
+                        for _ in 0..row.depth {
+                            ui.indent();
+                        }
+                        let id = ui.push_id(row.entity.id() as i32);
+                        if row.has_children {
+                            let toggle = if self.expanded.contains(&row.entity) { im_str!("-") } else { im_str!("+") };
+                            if ui.small_button(toggle) {
+                                actions.push(OutlinerAction::Toggle(row.entity));
+                            }
+                        } else {
+                            ui.text("  ");
+                        }
+                        ui.same_line(0.0);
+                        if Selectable::new(&row.label).selected(selected == Some(row.entity)).build(ui) {
+                            actions.push(OutlinerAction::Click(row.entity));
+                        }
+                        if !row.badges.is_empty() {
+                            ui.same_line(0.0);
+                            ui.text_disabled(&row.badges);
+                        }
+                        id.pop(ui);
+                        for _ in 0..row.depth {
+                            ui.unindent();
+                        }
+                    }
+                });
+        });
+
+        for action in actions {
+            match action {
+                OutlinerAction::Toggle(entity) => {
+                    if !self.expanded.remove(&entity) {
+                        self.expanded.insert(entity);
+                    }
+                }
+                OutlinerAction::Click(entity) => {
+                    if let (true, Some(selected)) = (self.picking_parent, selected) {
+                        self.picking_parent = false;
+                        reparent(selected, entity, &names, &mut parents, &mut transforms, &mut history);
+                    } else {
+                        state.selected = Some(entity);
+                    }
+                }
+                OutlinerAction::Rename => {
+                    if let Some(selected) = selected {
+                        let before = names.get(selected).map(|name| name.name.to_string());
+                        let after = Some(self.name.to_str().to_owned()).filter(|name| !name.is_empty());
+                        if before != after {
+                            match after {
+                                Some(ref name) => {
+                                    let _ = names.insert(selected, Named::new(name.clone()));
+                                }
+                                None => {
+                                    names.remove(selected);
+                                }
+                            }
+                            history.record(
+                                format!("Rename entity {}", selected.id()),
+                                vec![Edit::name(selected, before, after)],
+                            );
+                        }
+                    }
+                }
+                OutlinerAction::SetHidden(hide) => {
+                    if let Some(selected) = selected {
+                        let before = hidden.contains(selected);
+                        if hide {
+                            let _ = hidden.insert(selected, HiddenPropagate::default());
+                        } else {
+                            hidden.remove(selected);
+                        }
+                        history.record(
+                            format!("{} {}", if hide { "Hide" } else { "Show" }, display_name(selected, &names)),
+                            vec![Edit::hidden(selected, before, hide)],
+                        );
+                    }
+                }
+                OutlinerAction::Reparent(parent) => {
+                    if let Some(selected) = selected {
+                        reparent(selected, parent, &names, &mut parents, &mut transforms, &mut history);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn display_name(entity: Entity, names: &WriteStorage<'_, Named>) -> String {
+    match names.get(entity) {
+        Some(name) => name.name.to_string(),
+        None => format!("Entity {}", entity.id()),
+    }
+}
+
+/// Moves `entity` under `parent`, keeping its place in the world.
+fn reparent(
+    entity: Entity,
+    parent: Entity,
+    names: &WriteStorage<'_, Named>,
+    parents: &mut WriteStorage<'_, Parent>,
+    transforms: &mut WriteStorage<'_, Transform>,
+    history: &mut History,
+) {
+    let before = parents.get(entity).map(|parent| parent.entity);
+    if entity == parent || before == Some(parent) || scenes::is_descendant(parent, entity, &*parents) {
+        return;
+    }
+    let mut edits = Vec::new();
+    let parent_global = transforms
+        .get(parent)
+        .map_or_else(Matrix4::identity, |transform| *transform.global_matrix());
+    let local = parent_global.try_inverse().and_then(|inverse| {
+        let transform = transforms.get(entity)?;
+        Some((transform.clone(), transform_from_matrix(&(inverse * transform.global_matrix()))))
+    });
+    if let Some((before, after)) = local {
+        let _ = transforms.insert(entity, after.clone());
+        edits.push(Edit::transform(entity, before, after));
+    }
+    let _ = parents.insert(entity, Parent { entity: parent });
+    edits.push(Edit::parent(entity, before, Some(parent)));
+    history.record(format!("Reparent {}", display_name(entity, names)), edits);
+}
+
+/// Splits an affine matrix without shear into translation, rotation and scale.
+fn transform_from_matrix(matrix: &Matrix4<f32>) -> Transform {
+    let column = |i: usize| Vector3::new(matrix[(0, i)], matrix[(1, i)], matrix[(2, i)]);
+    let scale = Vector3::new(column(0).norm(), column(1).norm(), column(2).norm());
+    let mut transform = Transform::default();
+    transform.set_translation(column(3));
+    if scale.iter().all(|s| *s > std::f32::EPSILON) {
+        let rotation = Matrix3::from_columns(&[column(0) / scale.x, column(1) / scale.y, column(2) / scale.z]);
+        transform.set_rotation(UnitQuaternion::from_rotation_matrix(&Rotation3::from_matrix_unchecked(rotation)));
+    }
+    transform.set_scale(scale);
+    transform
+}
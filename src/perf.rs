@@ -0,0 +1,255 @@
+//! Frame timing and per-system timings shown in an imgui window.
+use amethyst::{
+    core::{
+        ecs::{Entities, Join, Read, ReadStorage, System, World},
+        SystemDesc,
+    },
+    renderer::light::Light,
+    utils::fps_counter::FpsCounter,
+};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    ui::{self, LIGHT_KINDS},
+    RenderMode,
+};
+
+/// Frames kept for the frame time graph and its statistics.
+const FRAME_WINDOW: usize = 240;
+/// Weight of the newest run in the smoothed system timings.
+const SMOOTHING: f32 = 0.05;
+
+pub fn millis(duration: Duration) -> f32 {
+    duration.as_secs() as f32 * 1000.0 + duration.subsec_nanos() as f32 / 1_000_000.0
+}
+
+/// Smoothed run time in milliseconds of every `Timed` system, shared with the systems themselves
+/// so recording does not add a resource dependency between them.
+#[derive(Clone, Debug, Default)]
+pub struct SystemTimings(Arc<Mutex<BTreeMap<&'static str, f32>>>);
+
+impl SystemTimings {
+    fn record(&self, name: &'static str, elapsed: Duration) {
+        let elapsed = millis(elapsed);
+        let mut timings = self.0.lock().unwrap();
+        let average = timings.entry(name).or_insert(elapsed);
+        *average += (elapsed - *average) * SMOOTHING;
+    }
+
+    /// Timings sorted from the slowest system.
+    pub fn slowest_first(&self) -> Vec<(&'static str, f32)> {
+        let mut timings = self.0.lock().unwrap().iter().map(|(name, ms)| (*name, *ms)).collect::<Vec<_>>();
+        timings.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        timings
+    }
+}
+
+/// Runs `system` and records how long it took under `name`.
+///
+/// The dispatcher does not expose timings, so only systems added through this wrapper are measured.
+pub struct Timed<S> {
+    name: &'static str,
+    system: S,
+    timings: SystemTimings,
+}
+
+impl<S> Timed<S> {
+    pub fn new(name: &'static str, system: S, timings: &SystemTimings) -> Self {
+        Self {
+            name,
+            system,
+            timings: timings.clone(),
+        }
+    }
+}
+
+impl<'a, S: System<'a>> System<'a> for Timed<S> {
+    type SystemData = S::SystemData;
+
+    fn run(&mut self, data: Self::SystemData) {
+        let start = Instant::now();
+        self.system.run(data);
+        self.timings.record(self.name, start.elapsed());
+    }
+}
+
+/// `Timed` for systems built from a `SystemDesc`.
+pub struct TimedDesc<D> {
+    name: &'static str,
+    desc: D,
+    timings: SystemTimings,
+}
+
+impl<D> TimedDesc<D> {
+    pub fn new(name: &'static str, desc: D, timings: &SystemTimings) -> Self {
+        Self {
+            name,
+            desc,
+            timings: timings.clone(),
+        }
+    }
+}
+
+impl<'a, 'b, S, D> SystemDesc<'a, 'b, Timed<S>> for TimedDesc<D>
+where
+    S: for<'c> System<'c> + Send + 'a,
+    D: SystemDesc<'a, 'b, S>,
+{
+    fn build(self, world: &mut World) -> Timed<S> {
+        Timed {
+            name: self.name,
+            system: self.desc.build(world),
+            timings: self.timings,
+        }
+    }
+}
+
+/// Frame time statistics over the sliding window, in milliseconds.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FrameStats {
+    pub min: f32,
+    pub avg: f32,
+    pub max: f32,
+    pub p99: f32,
+}
+
+impl FrameStats {
+    pub fn new(frame_times: &[f32]) -> Self {
+        if frame_times.is_empty() {
+            return Self::default();
+        }
+        let mut sorted = frame_times.to_vec();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        let p99 = ((sorted.len() - 1) as f32 * 0.99).round() as usize;
+        Self {
+            min: sorted[0],
+            avg: sorted.iter().sum::<f32>() / sorted.len() as f32,
+            max: sorted[sorted.len() - 1],
+            p99: sorted[p99],
+        }
+    }
+}
+
+/// Shows the FPS, a graph of recent frame times, the `Timed` system timings and the frame time
+/// they do not account for, entity and light counts and the render mode.
+///
+/// Frame times are wall-clock time between runs, so they stay real while fixed stepping is enabled.
+/// GPU time is not measured separately; it shows up in the frame time.
+pub struct PerfOverlaySystem {
+    timings: SystemTimings,
+    frame_times: VecDeque<f32>,
+    last_frame: Option<Instant>,
+}
+
+impl PerfOverlaySystem {
+    pub fn new(timings: &SystemTimings) -> Self {
+        Self {
+            timings: timings.clone(),
+            frame_times: VecDeque::with_capacity(FRAME_WINDOW),
+            last_frame: None,
+        }
+    }
+}
+
+impl<'a> System<'a> for PerfOverlaySystem {
+    type SystemData = (
+        Read<'a, FpsCounter>,
+        Read<'a, RenderMode>,
+        Entities<'a>,
+        ReadStorage<'a, Light>,
+    );
+
+    fn run(&mut self, (fps, mode, entities, lights): Self::SystemData) {
+        use amethyst_imgui::imgui::*;
+
+        let now = Instant::now();
+        if let Some(last_frame) = self.last_frame.replace(now) {
+            if self.frame_times.len() == FRAME_WINDOW {
+                self.frame_times.pop_front();
+            }
+            self.frame_times.push_back(millis(now - last_frame));
+        }
+        let frame_times = self.frame_times.iter().cloned().collect::<Vec<_>>();
+        let stats = FrameStats::new(&frame_times);
+
+        let entity_count = (&*entities).join().count();
+        let mut light_counts = [0; LIGHT_KINDS.len()];
+        for light in (&lights).join() {
+            light_counts[ui::light_kind(light)] += 1;
+        }
+        let system_timings = self.timings.slowest_first();
+        // Engine systems, rendering and waiting for the GPU are not wrapped in `Timed`.
+        let untimed = (stats.avg - system_timings.iter().map(|(_, ms)| ms).sum::<f32>()).max(0.0);
+
+        amethyst_imgui::with(|ui| {
+            Window::new(im_str!("Performance"))
+                .size([320.0, 360.0], Condition::FirstUseEver)
+                .build(ui, || {
+                    ui.text(&im_str!("{:.1} FPS, render mode {}", fps.sampled_fps(), mode.name()));
+                    ui.plot_lines(im_str!("##frame_times"), &frame_times)
+                        .graph_size([0.0, 60.0])
+                        .scale_min(0.0)
+                        .overlay_text(&im_str!("{:.2} ms", frame_times.last().cloned().unwrap_or(0.0)))
+                        .build();
+                    ui.text(&im_str!(
+                        "min {:.2}  avg {:.2}  max {:.2}  p99 {:.2} ms",
+                        stats.min,
+                        stats.avg,
+                        stats.max,
+                        stats.p99
+                    ));
+                    ui.separator();
+                    ui.text(&im_str!("{} entities", entity_count));
+                    for (name, count) in LIGHT_KINDS.iter().zip(light_counts.iter()) {
+                        if *count > 0 {
+                            ui.text(&im_str!("{} {} lights", count, name));
+                        }
+                    }
+                    if ui.collapsing_header(im_str!("Systems")).default_open(true).build() {
+                        ui.text_disabled(im_str!("Instrumented systems only"));
+                        for (name, ms) in &system_timings {
+                            ui.text(&im_str!("{:>8.3} ms  {}", ms, name));
+                        }
+                        ui.text(&im_str!("{:>8.3} ms  untimed (avg frame time minus the above)", untimed));
+                    }
+                });
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1.0e-5
+    }
+
+    #[test]
+    fn frame_stats_of_no_frames_are_zero() {
+        let stats = FrameStats::new(&[]);
+        assert!(close(stats.min, 0.0) && close(stats.avg, 0.0) && close(stats.max, 0.0) && close(stats.p99, 0.0));
+    }
+
+    #[test]
+    fn frame_stats_ignore_order() {
+        let stats = FrameStats::new(&[20.0, 10.0, 40.0, 30.0]);
+        assert!(close(stats.min, 10.0));
+        assert!(close(stats.avg, 25.0));
+        assert!(close(stats.max, 40.0));
+        assert!(close(stats.p99, 40.0));
+    }
+
+    #[test]
+    fn frame_stats_p99_skips_single_spikes() {
+        let mut frame_times = vec![16.0; 199];
+        frame_times.push(100.0);
+        let stats = FrameStats::new(&frame_times);
+        assert!(close(stats.p99, 16.0));
+        assert!(close(stats.max, 100.0));
+    }
+}
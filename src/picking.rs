@@ -0,0 +1,219 @@
+//! Selecting lights and meshes by clicking into the viewport.
+use amethyst::{
+    core::{
+        ecs::{Entities, Entity, Join, Read, ReadExpect, ReadStorage, System, SystemData, World, Write},
+        math::{Matrix4, Point2, Point3, Vector2, Vector3},
+        shrev::{EventChannel, ReaderId},
+        SystemDesc, Transform,
+    },
+    input::{InputEvent, InputHandler, StringBindings},
+    renderer::{
+        debug_drawing::DebugLines,
+        light::{AreaLight, Light},
+        palette::Srgba,
+        visibility::BoundingSphere,
+        ActiveCamera, Camera,
+    },
+    window::ScreenDimensions,
+    winit::MouseButton,
+};
+
+use crate::{filtered_input::FilteredInputEvent, gizmo::Gizmo, light_debug, light_proxy::LightProxy, ui::UIState};
+
+/// Cursor travel in pixels up to which a press and release still count as a click.
+const CLICK_TOLERANCE: f32 = 3.0;
+/// Pick radius of punctual lights, which have no extent of their own.
+const PUNCTUAL_PICK_RADIUS: f32 = 0.25;
+
+/// Selects the nearest light or mesh under the cursor on a left click that imgui did not take.
+///
+/// Left drags rotate the camera, so only a press and release at the same spot count.
+/// Presses that grab a gizmo handle are ignored.
+pub struct PickingSystem {
+    event_reader: ReaderId<FilteredInputEvent>,
+    press_position: Option<(f32, f32)>,
+}
+
+impl<'a> System<'a> for PickingSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Read<'a, EventChannel<FilteredInputEvent>>,
+        Read<'a, InputHandler<StringBindings>>,
+        ReadExpect<'a, ScreenDimensions>,
+        Read<'a, ActiveCamera>,
+        ReadStorage<'a, Camera>,
+        Entities<'a>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, Light>,
+        ReadStorage<'a, BoundingSphere>,
+        ReadStorage<'a, LightProxy>,
+        Read<'a, Gizmo>,
+        Write<'a, UIState>,
+        Write<'a, DebugLines>,
+    );
+
+    fn run(
+        &mut self,
+        (events, input, screen, active_camera, cameras, entities, transforms, lights, bounds, proxies, gizmo, mut state, mut debug_lines): Self::SystemData,
+    ) {
+        let mut clicked = None;
+        for event in events.read(&mut self.event_reader) {
+            if let FilteredInputEvent::Free(ref event) = *event {
+                match *event {
+                    InputEvent::MouseButtonPressed(MouseButton::Left) => {
+                        self.press_position = if gizmo.dragging { None } else { input.mouse_position() };
+                    }
+                    InputEvent::MouseButtonReleased(MouseButton::Left) => {
+                        if let (Some(pressed), Some(released)) = (self.press_position.take(), input.mouse_position()) {
+                            let travel = (pressed.0 - released.0).abs() + (pressed.1 - released.1).abs();
+                            if travel <= CLICK_TOLERANCE {
+                                clicked = Some(released);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        if let Some(position) = clicked {
+            let camera = active_camera
+                .entity
+                .and_then(|entity| Some((cameras.get(entity)?, transforms.get(entity)?)));
+            if let Some((camera, camera_transform)) = camera {
+                let (origin, direction) = cursor_ray(camera, camera_transform, &screen, position);
+
+                let mut nearest_light: Option<(f32, Entity, Point3<f32>)> = None;
+                let mut nearest_mesh: Option<(f32, Entity, (Point3<f32>, f32))> = None;
+                for (entity, transform, light, bound) in
+                    (&entities, &transforms, lights.maybe(), bounds.maybe()).join()
+                {
+                    if let Some(light) = light {
+                        let (center, radius) = light_bounds(transform, light);
+                        if let Some(distance) = intersect_sphere(&origin, &direction, &center, radius) {
+                            if nearest_light.map_or(true, |(nearest, _, _)| distance < nearest) {
+                                nearest_light = Some((distance, entity, center));
+                            }
+                        }
+                    } else if let Some(bound) = bound {
+                        let (center, radius) = mesh_bounds(transform, bound);
+                        if let Some(distance) = intersect_sphere(&origin, &direction, &center, radius) {
+                            // Clicking the visible surface of an area light selects the light.
+                            if let Some(proxy) = proxies.get(entity) {
+                                if nearest_light.map_or(true, |(nearest, _, _)| distance < nearest) {
+                                    nearest_light = Some((distance, proxy.light, center));
+                                }
+                            } else if nearest_mesh.map_or(true, |(nearest, _, _)| distance < nearest) {
+                                nearest_mesh = Some((distance, entity, (center, radius)));
+                            }
+                        }
+                    }
+                }
+                state.selected = match (nearest_light, nearest_mesh) {
+                    // Lights usually sit inside the bounds of the geometry around them, which would
+                    // otherwise always be hit first.
+                    (Some((light_distance, light, light_center)), Some((mesh_distance, mesh, (mesh_center, mesh_radius)))) => {
+                        if light_distance <= mesh_distance || (light_center - mesh_center).norm() <= mesh_radius {
+                            Some(light)
+                        } else {
+                            Some(mesh)
+                        }
+                    }
+                    (Some((_, light, _)), None) => Some(light),
+                    (None, Some((_, mesh, _))) => Some(mesh),
+                    (None, None) => None,
+                };
+            }
+        }
+
+        // Highlight the selection.
+        if let Some(selected) = state.selected {
+            let highlight = match (transforms.get(selected), lights.get(selected), bounds.get(selected)) {
+                (Some(transform), Some(light), _) => Some(light_bounds(transform, light)),
+                (Some(transform), None, Some(bound)) => Some(mesh_bounds(transform, bound)),
+                _ => None,
+            };
+            if let Some((center, radius)) = highlight {
+                light_debug::draw_wire_sphere(&mut debug_lines, center, radius * 1.05, Srgba::new(1.0, 0.5, 0.0, 1.0));
+            }
+        }
+    }
+}
+
+/// World space ray through the cursor `position`, with a normalized direction.
+pub fn cursor_ray(
+    camera: &Camera,
+    camera_transform: &Transform,
+    screen: &ScreenDimensions,
+    position: (f32, f32),
+) -> (Point3<f32>, Vector3<f32>) {
+    let ray = camera.projection().screen_ray(
+        Point2::new(position.0, position.1),
+        Vector2::new(screen.width(), screen.height()),
+        camera_transform,
+    );
+    (ray.origin, ray.direction.normalize())
+}
+
+pub fn max_scale(matrix: &Matrix4<f32>) -> f32 {
+    (0..3)
+        .map(|i| Vector3::new(matrix[(0, i)], matrix[(1, i)], matrix[(2, i)]).norm())
+        .fold(0.0, f32::max)
+}
+
+/// World space bounding sphere of a light.
+pub fn light_bounds(transform: &Transform, light: &Light) -> (Point3<f32>, f32) {
+    let matrix = transform.global_matrix();
+    let center = matrix.transform_point(&Point3::origin());
+    let radius = match *light {
+        Light::Area(AreaLight::Sphere(ref l)) => l.radius * max_scale(matrix),
+        // Disks and rectangles span -1..1 in their local XY plane.
+        Light::Area(_) => max_scale(matrix) * std::f32::consts::SQRT_2,
+        _ => PUNCTUAL_PICK_RADIUS,
+    };
+    (center, radius)
+}
+
+/// World space bounding sphere of a mesh.
+pub fn mesh_bounds(transform: &Transform, bound: &BoundingSphere) -> (Point3<f32>, f32) {
+    let matrix = transform.global_matrix();
+    (matrix.transform_point(&bound.center), bound.radius * max_scale(matrix))
+}
+
+/// Distance along the normalized `direction` at which the ray enters the sphere.
+///
+/// Spheres containing the ray origin are not hit, so the camera can pick things inside the bounds
+/// of the level around it.
+fn intersect_sphere(origin: &Point3<f32>, direction: &Vector3<f32>, center: &Point3<f32>, radius: f32) -> Option<f32> {
+    let offset = origin - center;
+    let b = offset.dot(direction);
+    let c = offset.norm_squared() - radius * radius;
+    let discriminant = b * b - c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    if c <= 0.0 {
+        return None;
+    }
+    let distance = -b - discriminant.sqrt();
+    if distance >= 0.0 {
+        Some(distance)
+    } else {
+        None
+    }
+}
+
+#[derive(Default)]
+pub struct PickingSystemDesc;
+impl<'a, 'b> SystemDesc<'a, 'b, PickingSystem> for PickingSystemDesc {
+    fn build(self, world: &mut World) -> PickingSystem {
+        <PickingSystem as System<'_>>::SystemData::setup(world);
+
+        let event_reader = Write::<EventChannel<FilteredInputEvent>>::fetch(world).register_reader();
+
+        PickingSystem {
+            event_reader,
+            press_position: None,
+        }
+    }
+}
@@ -2,14 +2,17 @@ use amethyst::{
     animation::AnimationSetPrefab,
     assets::{AssetPrefab, Handle, Prefab, PrefabData, ProgressCounter},
     controls::ControlTagPrefab,
-    core::{ecs::Entity, Transform, Named},
+    core::{
+        ecs::{Component, DenseVecStorage, Entity, WriteStorage},
+        Transform, Named,
+    },
     gltf::{GltfSceneAsset, GltfSceneFormat},
     utils::tag::Tag,
     Error,
     renderer::{
         camera::CameraPrefab,
         formats::{mesh::MeshPrefab, mtl::MaterialPrefab},
-        light::LightPrefab,
+        light::Light,
         rendy::mesh::{Normal, Position, Tangent, TexCoord},
         sprite::{
             prefab::{SpriteRenderPrefab, SpriteSheetPrefab},
@@ -43,26 +46,94 @@ pub struct Scene {
     pub animation_index: usize,
 }
 
-type GenMeshVertex = (Vec<Position>, Vec<Normal>, Vec<Tangent>, Vec<TexCoord>);
+/// Index of the prefab entry an entity was spawned from, used to write edits back.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PrefabIndex(pub usize);
+
+impl Component for PrefabIndex {
+    type Storage = DenseVecStorage<Self>;
+}
+
+/// Light of a scene entity.
+///
+/// Same layout as amethysts `LightPrefab`, but constructible so edited lights can be saved.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct LightData {
+    pub light: Option<Light>,
+}
+
+impl<'a> PrefabData<'a> for LightData {
+    type SystemData = WriteStorage<'a, Light>;
+    type Result = ();
+
+    fn add_to_entity(
+        &self,
+        entity: Entity,
+        lights: &mut Self::SystemData,
+        _: &[Entity],
+        _: &[Entity],
+    ) -> Result<(), Error> {
+        if let Some(ref light) = self.light {
+            lights.insert(entity, light.clone())?;
+        }
+        Ok(())
+    }
+}
+
+pub type GenMeshVertex = (Vec<Position>, Vec<Normal>, Vec<Tangent>, Vec<TexCoord>);
 
 #[derive(Derivative, Deserialize, Serialize)]
 #[derivative(Default(bound = ""))]
 #[serde(default)]
 pub struct ScenePrefabData {
-    transform: Option<Transform>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transform: Option<Transform>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     gltf: Option<AssetPrefab<GltfSceneAsset, GltfSceneFormat>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     sprite_sheet: Option<SpriteSheetPrefab>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     animation_set: Option<AnimationSetPrefab<SpriteAnimationId, SpriteRender>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     camera: Option<CameraPrefab>,
-    light: Option<LightPrefab>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub light: Option<LightData>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     tag: Option<Tag<AnimationMarker>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     fly_tag: Option<ControlTagPrefab>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     sprite: Option<SpriteRenderPrefab>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     mesh: Option<MeshPrefab<GenMeshVertex>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     material: Option<MaterialPrefab>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     transparent: Option<Transparent>,
 }
 
+impl ScenePrefabData {
+    /// Entity holding only a light.
+    pub fn with_light(transform: Transform, light: Light) -> Self {
+        Self {
+            transform: Some(transform),
+            light: Some(LightData { light: Some(light) }),
+            ..Default::default()
+        }
+    }
+
+    /// Entity rendering a mesh, used by scenes generated in code.
+    pub fn with_mesh(transform: Transform, mesh: MeshPrefab<GenMeshVertex>, material: MaterialPrefab) -> Self {
+        Self {
+            transform: Some(transform),
+            mesh: Some(mesh),
+            material: Some(material),
+            ..Default::default()
+        }
+    }
+}
+
 type PData<'a, T> = <T as PrefabData<'a>>::SystemData;
 impl<'a> PrefabData<'a> for ScenePrefabData {
     #[allow(clippy::type_complexity)]
@@ -72,12 +143,13 @@ impl<'a> PrefabData<'a> for ScenePrefabData {
         PData<'a, SpriteSheetPrefab>,
         PData<'a, AnimationSetPrefab<SpriteAnimationId, SpriteRender>>,
         PData<'a, CameraPrefab>,
-        PData<'a, LightPrefab>,
+        PData<'a, LightData>,
         PData<'a, Tag<AnimationMarker>>,
         PData<'a, ControlTagPrefab>,
         PData<'a, SpriteRenderPrefab>,
         PData<'a, MeshPrefab<GenMeshVertex>>,
         PData<'a, MaterialPrefab>,
+        WriteStorage<'a, PrefabIndex>,
     );
     type Result = ();
     fn add_to_entity(
@@ -135,6 +207,9 @@ impl<'a> PrefabData<'a> for ScenePrefabData {
             .as_ref()
             .map(|p| p.add_to_entity(entity, &mut d.0, e, c))
             .transpose()?;
+        if let Some(index) = e.iter().position(|other| *other == entity) {
+            d.11.insert(entity, PrefabIndex(index))?;
+        }
         Ok(())
     }
     fn load_sub_assets(
@@ -0,0 +1,290 @@
+//! Render graph side of screenshots: an offscreen 3D pass and a node copying it to host memory.
+use amethyst::{
+    core::ecs::{DispatcherBuilder, Read, SystemData, World},
+    error::Error,
+    renderer::{
+        bundle::{ImageOptions, OutputColor, RenderPlan, RenderPlugin, Target, TargetImage, TargetPlanOutputs},
+        rendy::{
+            command::{CommandPool, Family, Fence, OneShot, Queue, Submission, Transfer},
+            factory::Factory,
+            frame::Frames,
+            graph::{
+                gfx_acquire_barriers, gfx_release_barriers, BufferAccess, GraphContext, ImageAccess, Node,
+                NodeBuffer, NodeDesc, NodeImage,
+            },
+            hal::{self, command::{ClearDepthStencil, ClearValue}, format::Format, image::Kind, Backend},
+            memory::Download,
+            resource::{Buffer, BufferInfo, Escape},
+        },
+        types::DefaultBackend,
+        RenderFlat3D, RenderPbr3D, RenderShaded3D,
+    },
+    window::ScreenDimensions,
+};
+
+use crate::{
+    screenshot::{Capture, Screenshots},
+    RenderMode, CLEAR_COLOR,
+};
+
+const SCREENSHOT_TARGET: Target = Target::Custom("screenshot");
+
+/// Renders the 3D view a second time into an offscreen image while a screenshot is pending and
+/// reads it back into `Screenshots::captured`.
+///
+/// The window surface can not be read back, so the screenshot pass has its own color target.
+/// It is only part of the graph for the frames a screenshot waits, since adding or removing it
+/// rebuilds the graph.
+#[derive(Debug)]
+pub struct RenderScreenshots {
+    pbr: RenderPbr3D,
+    shaded: RenderShaded3D,
+    flat: RenderFlat3D,
+    planned: bool,
+    dimensions: Option<ScreenDimensions>,
+}
+
+impl Default for RenderScreenshots {
+    fn default() -> Self {
+        Self {
+            pbr: RenderPbr3D::default().with_target(SCREENSHOT_TARGET),
+            shaded: RenderShaded3D::default().with_target(SCREENSHOT_TARGET),
+            flat: RenderFlat3D::default().with_target(SCREENSHOT_TARGET),
+            planned: false,
+            dimensions: None,
+        }
+    }
+}
+
+impl RenderScreenshots {
+    fn pending(world: &World) -> bool {
+        world.try_fetch::<Screenshots>().map_or(false, |screenshots| screenshots.is_pending())
+    }
+}
+
+impl RenderPlugin<DefaultBackend> for RenderScreenshots {
+    fn on_build<'a, 'b>(&mut self, _world: &mut World, _builder: &mut DispatcherBuilder<'a, 'b>) -> Result<(), Error> {
+        // The draw groups' systems are already added by `RenderSwitchable3D`.
+        Ok(())
+    }
+
+    fn should_rebuild(&mut self, world: &World) -> bool {
+        let dimensions = <Read<'_, ScreenDimensions>>::fetch(world);
+        Self::pending(world) != self.planned || (self.planned && self.dimensions.as_ref() != Some(&dimensions))
+    }
+
+    fn on_plan(
+        &mut self,
+        plan: &mut RenderPlan<DefaultBackend>,
+        factory: &mut Factory<DefaultBackend>,
+        world: &World,
+    ) -> Result<(), Error> {
+        self.planned = Self::pending(world);
+        if !self.planned {
+            return Ok(());
+        }
+        let dimensions = <Read<'_, ScreenDimensions>>::fetch(world).clone();
+        let (width, height) = (dimensions.width() as u32, dimensions.height() as u32);
+        self.dimensions = Some(dimensions);
+        let kind = Kind::D2(width, height, 1, 1);
+
+        plan.define_pass(
+            SCREENSHOT_TARGET,
+            TargetPlanOutputs {
+                colors: vec![OutputColor::Image(ImageOptions {
+                    kind,
+                    levels: 1,
+                    format: Format::Rgba8Srgb,
+                    clear: Some(ClearValue::Color(CLEAR_COLOR.into())),
+                })],
+                depth: Some(ImageOptions {
+                    kind,
+                    levels: 1,
+                    format: Format::D32Sfloat,
+                    clear: Some(ClearValue::DepthStencil(ClearDepthStencil(1.0, 0))),
+                }),
+            },
+        )?;
+        match *<Read<'_, RenderMode>>::fetch(world) {
+            RenderMode::Pbr => self.pbr.on_plan(plan, factory, world)?,
+            RenderMode::Shaded => self.shaded.on_plan(plan, factory, world)?,
+            RenderMode::Flat => self.flat.on_plan(plan, factory, world)?,
+        }
+
+        // Nothing presented depends on the screenshot target, so the main target pulls it in.
+        plan.extend_target(Target::Main, move |ctx| {
+            let image = ctx.get_image(TargetImage::Color(SCREENSHOT_TARGET, 0))?;
+            let pass = ctx.get_node(SCREENSHOT_TARGET)?;
+            let readback = ctx
+                .graph()
+                .add_node(ReadbackDesc { width, height }.builder().with_image(image).with_dependency(pass));
+            ctx.add_dep(readback);
+            Ok(())
+        });
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct ReadbackDesc {
+    width: u32,
+    height: u32,
+}
+
+impl<B: Backend> NodeDesc<B, World> for ReadbackDesc {
+    type Node = Readback<B>;
+
+    fn buffers(&self) -> Vec<BufferAccess> {
+        Vec::new()
+    }
+
+    fn images(&self) -> Vec<ImageAccess> {
+        vec![ImageAccess {
+            access: hal::image::Access::TRANSFER_READ,
+            usage: hal::image::Usage::TRANSFER_SRC,
+            layout: hal::image::Layout::TransferSrcOptimal,
+            stages: hal::pso::PipelineStage::TRANSFER,
+        }]
+    }
+
+    fn build<'a>(
+        self,
+        _ctx: &GraphContext<B>,
+        factory: &mut Factory<B>,
+        family: &mut Family<B>,
+        _queue: usize,
+        _aux: &World,
+        _buffers: Vec<NodeBuffer>,
+        images: Vec<NodeImage>,
+    ) -> Result<Self::Node, failure::Error> {
+        let pool = factory
+            .create_command_pool(family)?
+            .with_capability::<Transfer>()
+            .map_err(|_| failure::format_err!("Screenshot readback needs a queue supporting transfers"))?;
+        let buffer = factory.create_buffer(
+            BufferInfo {
+                size: u64::from(self.width) * u64::from(self.height) * 4,
+                usage: hal::buffer::Usage::TRANSFER_DST,
+            },
+            Download,
+        )?;
+        Ok(Readback {
+            pool,
+            buffer,
+            image: images.into_iter().next().expect("readback declares one image"),
+            width: self.width,
+            height: self.height,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Readback<B: Backend> {
+    pool: CommandPool<B, Transfer>,
+    buffer: Escape<Buffer<B>>,
+    image: NodeImage,
+    width: u32,
+    height: u32,
+}
+
+impl<B: Backend> Readback<B> {
+    /// Copies the buffer filled by the last submission into a `Capture`.
+    unsafe fn read(&mut self, factory: &Factory<B>) -> Result<Capture, failure::Error> {
+        let size = u64::from(self.width) * u64::from(self.height) * 4;
+        let mut mapped = self.buffer.map(factory.device(), 0..size)?;
+        let pixels = mapped.read::<u8>(factory.device(), 0..size)?.to_vec();
+        Ok(Capture {
+            width: self.width,
+            height: self.height,
+            pixels,
+        })
+    }
+}
+
+impl<B: Backend> Node<B, World> for Readback<B> {
+    type Capability = Transfer;
+    type Desc = ReadbackDesc;
+
+    unsafe fn run<'a>(
+        &mut self,
+        ctx: &GraphContext<B>,
+        factory: &Factory<B>,
+        queue: &mut Queue<B>,
+        aux: &World,
+        _frames: &Frames<B>,
+        waits: &[(&'a B::Semaphore, hal::pso::PipelineStage)],
+        signals: &[&'a B::Semaphore],
+        fence: Option<&mut Fence<B>>,
+    ) {
+        let mut screenshots = aux.fetch_mut::<Screenshots>();
+        let copy = screenshots.is_pending() && screenshots.captured.is_none();
+
+        let command_buffer = self.pool.allocate_buffers(1).pop().unwrap();
+        let mut recording = command_buffer.begin(OneShot, ());
+        let mut encoder = recording.encoder();
+        {
+            let (stages, barriers) = gfx_acquire_barriers(ctx, None, Some(&self.image));
+            if !barriers.is_empty() {
+                encoder.pipeline_barrier(stages, hal::memory::Dependencies::empty(), barriers);
+            }
+        }
+        if copy {
+            let image = ctx.get_image(self.image.id).expect("screenshot image exists");
+            encoder.copy_image_to_buffer(
+                image.raw(),
+                hal::image::Layout::TransferSrcOptimal,
+                self.buffer.raw(),
+                Some(hal::command::BufferImageCopy {
+                    buffer_offset: 0,
+                    buffer_width: self.width,
+                    buffer_height: self.height,
+                    image_layers: hal::image::SubresourceLayers {
+                        aspects: hal::format::Aspects::COLOR,
+                        level: 0,
+                        layers: 0..1,
+                    },
+                    image_offset: hal::image::Offset::ZERO,
+                    image_extent: hal::image::Extent {
+                        width: self.width,
+                        height: self.height,
+                        depth: 1,
+                    },
+                }),
+            );
+        }
+        {
+            let (stages, barriers) = gfx_release_barriers(ctx, None, Some(&self.image));
+            if !barriers.is_empty() {
+                encoder.pipeline_barrier(stages, hal::memory::Dependencies::empty(), barriers);
+            }
+        }
+
+        let (submit, command_buffer) = recording.finish().submit_once();
+        queue.submit(
+            Some(
+                Submission::new()
+                    .submits(Some(submit))
+                    .wait(waits.iter().cloned())
+                    .signal(signals.iter().cloned()),
+            ),
+            fence,
+        );
+
+        // The node only exists while a screenshot is pending, so stalling the queue here is simpler
+        // than keeping the command buffer and the pixels around until the frame completes.
+        let _ = queue.wait_idle();
+        self.pool.free_buffers(Some(command_buffer.mark_complete()));
+
+        if copy {
+            match self.read(factory) {
+                Ok(capture) => screenshots.captured = Some(capture),
+                Err(e) => screenshots.fail(format!("Failed to read back screenshot: {}", e)),
+            }
+        }
+    }
+
+    unsafe fn dispose(mut self, factory: &mut Factory<B>, _aux: &World) {
+        self.pool.reset();
+        factory.destroy_command_pool(self.pool);
+    }
+}
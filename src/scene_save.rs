@@ -0,0 +1,223 @@
+//! Writes the live state of the current scene back into a prefab file.
+use amethyst::{
+    assets::{Prefab, PrefabLoader, RonFormat},
+    core::{
+        ecs::{Entities, Join, Read, ReadStorage, System, Write},
+        Parent, Transform,
+    },
+    renderer::light::Light,
+    Error,
+};
+use ron::ser::PrettyConfig;
+use std::{fs, path::Path};
+
+use crate::{
+    light_proxy::LightProxy,
+    prefab_data::{LightData, PrefabIndex, Scene, ScenePrefabData},
+    scenes::{self, SceneInfo, SceneMap, PREFAB_DIR},
+};
+
+const PREFAB_HEADER: &str = "#![enable(implicit_some)]
+/*!
+    @import /amethyst_assets/src/prefab/mod.rs#Prefab
+    @import ../../src/prefab_data.rs#ScenePrefabData
+    Prefab<ScenePrefabData>
+*/
+
+";
+
+#[derive(Clone, Debug)]
+pub enum SaveTarget {
+    /// Overwrite the prefab the current scene was loaded from.
+    Overwrite,
+    /// Write a new prefab named `<name>.ron` next to the current one.
+    Variant(String),
+}
+
+/// Save requests from the UI and the outcome of the last save.
+#[derive(Default, Debug)]
+pub struct SceneSave {
+    pub request: Option<SaveTarget>,
+    pub status: String,
+}
+
+#[derive(Default, Debug)]
+pub struct SceneSaveSystem;
+
+impl<'a> System<'a> for SceneSaveSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Write<'a, SceneSave>,
+        Read<'a, Scene>,
+        Write<'a, SceneMap>,
+        PrefabLoader<'a, ScenePrefabData>,
+        Entities<'a>,
+        ReadStorage<'a, Parent>,
+        ReadStorage<'a, PrefabIndex>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, Light>,
+        ReadStorage<'a, LightProxy>,
+    );
+
+    fn run(
+        &mut self,
+        (mut save, scene, mut scene_map, loader, entities, parents, indices, transforms, lights, proxies): Self::SystemData,
+    ) {
+        let target = match save.request.take() {
+            Some(target) => target,
+            None => return,
+        };
+        let (index, root) = match (scene.scene, scene.entity) {
+            (Some(index), Some(root)) => (index, root),
+            _ => {
+                save.status = "No scene loaded.".into();
+                return;
+            }
+        };
+        let info = scene_map.get(index).unwrap().info.clone();
+        if info.procedural {
+            save.status = format!("{} is generated and cannot be saved.", info.display_name);
+            return;
+        }
+        let file_path = match target {
+            SaveTarget::Overwrite => info.file_path.clone(),
+            SaveTarget::Variant(ref name) => {
+                if let Err(e) = check_variant_name(name) {
+                    save.status = e;
+                    return;
+                }
+                let file_path = info.file_path.with_file_name(format!("{}.ron", name));
+                let existing = scene_map.index_of(name).and_then(|index| scene_map.get(index));
+                if file_path.exists() || existing.map_or(false, |entry| entry.info.file_path != file_path) {
+                    save.status = format!("A scene named {} already exists, pick another name.", name);
+                    return;
+                }
+                file_path
+            }
+        };
+
+        let mut prefab = match read_prefab(&info.file_path) {
+            Ok(prefab) => prefab,
+            Err(e) => {
+                save.status = format!("Failed to read {}: {}", info.file_path.display(), e);
+                return;
+            }
+        };
+
+        // Lights are rebuilt from the live entities, so lights that no longer exist are dropped.
+        for index in 0..prefab.len() {
+            if let Some(data) = prefab.entity(index).and_then(|e| e.data_mut()) {
+                data.light = None;
+            }
+        }
+        for (entity, transform, light, index) in (&entities, &transforms, lights.maybe(), indices.maybe()).join() {
+            if !scenes::is_descendant(entity, root, &parents) {
+                continue;
+            }
+            // Only prefab entities and lights are written back; proxy meshes are rebuilt from their
+            // light and nested nodes of loaded models come from the model file.
+            if proxies.contains(entity) || (index.is_none() && light.is_none()) {
+                continue;
+            }
+            // Transforms are relative to the parent, so the prefab must keep the live hierarchy.
+            let parent = parents.get(entity).map(|parent| parent.entity);
+            let parent_index = match parent {
+                Some(parent) if parent == root => Some(0),
+                Some(parent) => indices.get(parent).map(|index| index.0),
+                None => None,
+            };
+            let parent_index = match parent_index {
+                Some(parent_index) => parent_index,
+                None => {
+                    save.status = format!(
+                        "Entity {} is parented to an entity that is not part of the prefab, move it before saving.",
+                        entity.id()
+                    );
+                    return;
+                }
+            };
+            let light = light.map(|light| LightData {
+                light: Some(light.clone()),
+            });
+            match index.and_then(|index| prefab.entity(index.0)) {
+                Some(prefab_entity) => {
+                    if prefab_entity.parent() != Some(parent_index) {
+                        prefab_entity.set_parent(parent_index);
+                    }
+                    let data = prefab_entity.data_or_default();
+                    data.transform = Some(transform.clone());
+                    data.light = light;
+                }
+                None => {
+                    if let Some(light) = light.and_then(|light| light.light) {
+                        prefab.add(Some(parent_index), Some(ScenePrefabData::with_light(transform.clone(), light)));
+                    }
+                }
+            }
+        }
+
+        if let Err(e) = write_prefab(&file_path, &prefab) {
+            save.status = format!("Failed to write {}: {}", file_path.display(), e);
+            return;
+        }
+        save.status = format!("Saved {}.", file_path.display());
+
+        if let SaveTarget::Variant(name) = target {
+            let asset_path = Path::new(PREFAB_DIR)
+                .join(file_path.file_name().unwrap())
+                .to_string_lossy()
+                .into_owned();
+            let handle = loader.load(asset_path.as_str(), RonFormat, ());
+            let info = SceneInfo {
+                display_name: name.clone(),
+                name,
+                asset_path,
+                file_path,
+                ..info
+            };
+            // A scene of that name whose file was removed since it was loaded is replaced.
+            match scene_map.index_of(&info.name) {
+                Some(index) => scene_map.replace(index, info, handle),
+                None => {
+                    scene_map.insert(info, handle);
+                }
+            }
+        }
+    }
+}
+
+/// Variants are written next to the scene's prefab, so the name must be a plain file stem.
+fn check_variant_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        Err("Enter a name for the variant.".into())
+    } else if name.contains(|c: char| c == '/' || c == '\\' || c == ':') || name.contains("..") {
+        Err(format!("`{}` is not a valid variant name, path separators and `..` are not allowed.", name))
+    } else {
+        Ok(())
+    }
+}
+
+fn read_prefab(path: &Path) -> Result<Prefab<ScenePrefabData>, Error> {
+    let contents = fs::read_to_string(path)?;
+    Ok(ron::de::from_str(&contents)?)
+}
+
+fn write_prefab(path: &Path, prefab: &Prefab<ScenePrefabData>) -> Result<(), Error> {
+    let contents = ron::ser::to_string_pretty(prefab, PrettyConfig::default())?;
+    fs::write(path, format!("{}{}\n", PREFAB_HEADER, contents))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variant_names_are_plain_file_stems() {
+        assert!(check_variant_name("sponza_night").is_ok());
+        assert!(check_variant_name("sponza.v2").is_ok());
+        for name in &["", "  ", "../sponza", "lights/sponza", "lights\\sponza", "C:sponza", "a..b"] {
+            assert!(check_variant_name(name).is_err(), "`{}` was accepted", name);
+        }
+    }
+}
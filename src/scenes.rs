@@ -0,0 +1,272 @@
+use amethyst::{
+    assets::{Handle, Prefab},
+    core::{
+        math::{Quaternion, UnitQuaternion, Vector3},
+        Transform, Parent,
+    },
+    ecs::{storage::MaskedStorage, Entity, Storage},
+    utils::auto_fov::AutoFov,
+    Error,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+
+use crate::{camera_path::CAMERA_PATH_SUFFIX, prefab_data::ScenePrefabData};
+
+/// Directory inside the assets directory that is scanned for scene prefabs.
+pub const PREFAB_DIR: &str = "prefab";
+
+/// Location of the scene manifest inside the assets directory.
+pub const MANIFEST_PATH: &str = "config/scenes.ron";
+
+/// Camera placement applied when a scene is entered.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CameraPose {
+    pub position: [f32; 3],
+    /// Point the camera looks at.
+    pub target: [f32; 3],
+    /// Horizontal field of view in degrees.
+    pub fov: f32,
+}
+
+impl Default for CameraPose {
+    fn default() -> Self {
+        Self {
+            position: [0.0, 2.0, 4.0],
+            target: [0.0, 2.0, 0.0],
+            fov: 60.0,
+        }
+    }
+}
+
+impl CameraPose {
+    pub fn apply(&self, transform: &mut Transform, auto_fov: &mut AutoFov) {
+        transform.set_translation_xyz(self.position[0], self.position[1], self.position[2]);
+        transform.face_towards(Vector3::from(self.target), Vector3::y());
+        auto_fov.set_base_fovx(self.fov.to_radians());
+    }
+}
+
+/// Exact camera placement, as stored in bookmarks and camera paths.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct CameraView {
+    pub position: [f32; 3],
+    /// Orientation as a quaternion, `[x, y, z, w]`.
+    pub rotation: [f32; 4],
+    /// Horizontal field of view in degrees.
+    pub fov: f32,
+}
+
+impl CameraView {
+    pub fn new(position: Vector3<f32>, rotation: UnitQuaternion<f32>, fov: f32) -> Self {
+        let rotation = rotation.quaternion().coords;
+        Self {
+            position: [position.x, position.y, position.z],
+            rotation: [rotation.x, rotation.y, rotation.z, rotation.w],
+            fov,
+        }
+    }
+
+    /// Placement of a camera, with the default FOV of `CameraPose` if it has no `AutoFov`.
+    pub fn capture(transform: &Transform, auto_fov: Option<&AutoFov>) -> Self {
+        Self::new(
+            *transform.translation(),
+            *transform.rotation(),
+            auto_fov.map_or(CameraPose::default().fov, |auto_fov| auto_fov.base_fovx().to_degrees()),
+        )
+    }
+
+    pub fn rotation(&self) -> UnitQuaternion<f32> {
+        let [x, y, z, w] = self.rotation;
+        UnitQuaternion::from_quaternion(Quaternion::new(w, x, y, z))
+    }
+
+    pub fn apply(&self, transform: &mut Transform, auto_fov: Option<&mut AutoFov>) {
+        transform.set_translation(Vector3::from(self.position));
+        transform.set_rotation(self.rotation());
+        if let Some(auto_fov) = auto_fov {
+            auto_fov.set_base_fovx(self.fov.to_radians());
+        }
+    }
+}
+
+/// Contents of `config/scenes.ron`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SceneManifest {
+    pub scenes: Vec<SceneManifestEntry>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SceneManifestEntry {
+    /// Stable key of the scene.
+    pub name: String,
+    /// Prefab path relative to the assets directory.
+    pub prefab: String,
+    #[serde(default)]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub camera: CameraPose,
+}
+
+impl SceneManifest {
+    /// Loads the manifest, falling back to an empty one if the file does not exist.
+    pub fn load(assets_dir: &Path) -> Result<Self, Error> {
+        let path = assets_dir.join(MANIFEST_PATH);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = fs::read_to_string(&path)?;
+        Ok(ron::de::from_str(&contents)?)
+    }
+}
+
+/// A scene prefab found on disk.
+#[derive(Clone, Debug)]
+pub struct SceneInfo {
+    /// Stable key of the scene, taken from the manifest or the file stem of its prefab.
+    pub name: String,
+    /// Path of the prefab relative to the assets directory, as handed to the loader.
+    pub asset_path: String,
+    /// Absolute path of the prefab file.
+    pub file_path: PathBuf,
+    pub display_name: String,
+    pub description: String,
+    pub camera: CameraPose,
+    /// Built in code rather than loaded from `asset_path`.
+    pub procedural: bool,
+}
+
+/// Scans `<assets_dir>/prefab` for `*.ron` files that parse as `Prefab<ScenePrefabData>`
+/// and merges them with the entries of the scene manifest.
+///
+/// Scenes listed in the manifest come first, in manifest order, followed by the remaining
+/// prefabs sorted by name, so scene indices are stable between runs.
+pub fn discover_scenes(assets_dir: &Path) -> Result<Vec<SceneInfo>, Error> {
+    let mut scenes = Vec::new();
+    for entry in fs::read_dir(assets_dir.join(PREFAB_DIR))? {
+        let file_path = entry?.path();
+        if file_path.extension().map_or(true, |ext| ext != "ron")
+            || file_path.to_string_lossy().ends_with(CAMERA_PATH_SUFFIX)
+        {
+            continue;
+        }
+        let contents = fs::read_to_string(&file_path)?;
+        if let Err(e) = ron::de::from_str::<Prefab<ScenePrefabData>>(&contents) {
+            println!("Skipping {}: not a scene prefab ({}).", file_path.display(), e);
+            continue;
+        }
+        let file_name = file_path.file_name().unwrap();
+        let name = file_path.file_stem().unwrap().to_string_lossy().into_owned();
+        scenes.push(SceneInfo {
+            display_name: name.clone(),
+            name,
+            asset_path: Path::new(PREFAB_DIR)
+                .join(file_name)
+                .to_string_lossy()
+                .into_owned(),
+            file_path: file_path.clone(),
+            description: String::new(),
+            camera: CameraPose::default(),
+            procedural: false,
+        });
+    }
+    scenes.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut ordered = Vec::with_capacity(scenes.len());
+    for entry in SceneManifest::load(assets_dir)?.scenes {
+        let prefab = Path::new(&entry.prefab);
+        match scenes.iter().position(|s| Path::new(&s.asset_path) == prefab) {
+            Some(index) => {
+                let mut info = scenes.remove(index);
+                info.display_name = entry.display_name.unwrap_or_else(|| entry.name.clone());
+                info.name = entry.name;
+                info.description = entry.description;
+                info.camera = entry.camera;
+                ordered.push(info);
+            }
+            None => println!(
+                "Scene manifest entry {} refers to missing prefab {}.",
+                entry.name, entry.prefab
+            ),
+        }
+    }
+    ordered.extend(scenes);
+    Ok(ordered)
+}
+
+/// A loaded scene prefab.
+pub struct SceneEntry {
+    pub info: SceneInfo,
+    pub handle: Handle<Prefab<ScenePrefabData>>,
+}
+
+/// All scenes the lightroom can switch between, indexed by `UIState::scene`.
+#[derive(Default)]
+pub struct SceneMap {
+    entries: Vec<SceneEntry>,
+}
+
+impl SceneMap {
+    /// Adds a scene and returns its index.
+    pub fn insert(&mut self, info: SceneInfo, handle: Handle<Prefab<ScenePrefabData>>) -> usize {
+        self.entries.push(SceneEntry { info, handle });
+        self.entries.len() - 1
+    }
+
+    pub fn get(&self, index: usize) -> Option<&SceneEntry> {
+        self.entries.get(index)
+    }
+
+    /// Swaps the prefab of a scene, e.g. after a procedural scene was regenerated.
+    pub fn set_handle(&mut self, index: usize, handle: Handle<Prefab<ScenePrefabData>>) {
+        if let Some(entry) = self.entries.get_mut(index) {
+            entry.handle = handle;
+        }
+    }
+
+    /// Replaces a scene, e.g. after a variant was saved under the name of a scene whose file is gone.
+    pub fn replace(&mut self, index: usize, info: SceneInfo, handle: Handle<Prefab<ScenePrefabData>>) {
+        if let Some(entry) = self.entries.get_mut(index) {
+            *entry = SceneEntry { info, handle };
+        }
+    }
+
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.entries.iter().position(|e| e.info.name == name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &SceneEntry> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/// Returns whether `entity` is a (transitive) child of `root`.
+pub fn is_descendant<D>(entity: Entity, root: Entity, parents: &Storage<'_, Parent, D>) -> bool
+where
+    D: Deref<Target = MaskedStorage<Parent>>,
+{
+    let mut current = entity;
+    while let Some(parent) = parents.get(current) {
+        if parent.entity == root {
+            return true;
+        }
+        current = parent.entity;
+    }
+    false
+}
@@ -0,0 +1,284 @@
+//! PNG screenshots of the 3D view, each with a RON sidecar describing how to reproduce it.
+use amethyst::{
+    core::{
+        ecs::{Entities, Join, Read, ReadStorage, System, Write},
+        math::Point3,
+        shrev::{EventChannel, ReaderId},
+        Named, Transform,
+    },
+    input::InputEvent,
+    renderer::{light::Light, ActiveCamera},
+    utils::auto_fov::AutoFov,
+    winit::VirtualKeyCode,
+    Error,
+};
+use amethyst_derive::SystemDesc;
+use derive_new::new;
+use ron::ser::PrettyConfig;
+use serde::Serialize;
+use std::{
+    fs,
+    io::BufWriter,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    filtered_input::FilteredInputEvent,
+    fixed_step::FixedStep,
+    prefab_data::Scene,
+    scenes::{CameraView, SceneMap},
+    RenderMode,
+};
+
+/// Screenshot directory used when `--screenshot-dir` is not given, relative to the application root.
+pub const DEFAULT_DIR: &str = "screenshots";
+
+/// Pixels read back from the screenshot target, tightly packed RGBA8 rows starting at the top.
+#[derive(Clone, Debug)]
+pub struct Capture {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+/// Everything needed to render a screenshot again, written next to the image.
+#[derive(Clone, Debug, Serialize)]
+pub struct ScreenshotMetadata {
+    pub scene: String,
+    pub mode: &'static str,
+    pub camera: Option<CameraView>,
+    pub lights: Vec<LightRecord>,
+    /// Frame and simulation time if fixed stepping was enabled.
+    pub fixed_step: Option<(u64, f32)>,
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub build: BuildInfo,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LightRecord {
+    pub entity: u32,
+    pub name: Option<String>,
+    pub transform: Transform,
+    pub world_position: [f32; 3],
+    pub light: Light,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    pub profile: &'static str,
+    pub target: String,
+    pub features: Vec<&'static str>,
+}
+
+impl BuildInfo {
+    pub fn current() -> Self {
+        let mut features = Vec::new();
+        if cfg!(feature = "vulkan") {
+            features.push("vulkan");
+        }
+        if cfg!(feature = "renderdoc") {
+            features.push("renderdoc");
+        }
+        if cfg!(feature = "profiler") {
+            features.push("profiler");
+        }
+        Self {
+            version: env!("CARGO_PKG_VERSION"),
+            profile: if cfg!(debug_assertions) { "debug" } else { "release" },
+            target: format!("{}-{}", std::env::consts::ARCH, std::env::consts::OS),
+            features,
+        }
+    }
+}
+
+/// A screenshot waiting for its pixels; the metadata is taken when it is requested, so it matches
+/// the frame that is read back.
+#[derive(Clone, Debug)]
+struct PendingScreenshot {
+    image: PathBuf,
+    metadata: ScreenshotMetadata,
+}
+
+/// Screenshot requests and their outcome.
+///
+/// Setting `request` adds an offscreen pass rendering the 3D view to the render graph for one
+/// frame, which `RenderScreenshots` reads back into `captured`. The UI and debug lines are not
+/// part of the image.
+#[derive(Debug, Default)]
+pub struct Screenshots {
+    pub dir: PathBuf,
+    pub request: bool,
+    pending: Option<PendingScreenshot>,
+    pub captured: Option<Capture>,
+    pub status: String,
+}
+
+impl Screenshots {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            ..Default::default()
+        }
+    }
+
+    /// Whether a screenshot waits for the render graph to read it back.
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Drops the pending screenshot after its pixels could not be read back, so the screenshot
+    /// pass leaves the render graph again.
+    pub fn fail(&mut self, message: String) {
+        self.pending = None;
+        self.status = message;
+    }
+
+    /// Picks an unused file name, `<scene>-<mode>-<timestamp>[-n].png`.
+    fn next_path(&self, metadata: &ScreenshotMetadata) -> PathBuf {
+        let stem = file_stem(&format!("{}-{}-{}", metadata.scene, metadata.mode, metadata.timestamp));
+        let mut path = self.dir.join(format!("{}.png", stem));
+        let mut index = 1;
+        while path.exists() {
+            path = self.dir.join(format!("{}-{}.png", stem, index));
+            index += 1;
+        }
+        path
+    }
+}
+
+/// `name` with everything but ASCII letters, digits, `_` and `-` replaced by `_`, since scene
+/// names come from the manifest and may contain path separators.
+fn file_stem(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '-' { c } else { '_' })
+        .collect()
+}
+
+fn write_png(path: &Path, capture: &Capture) -> Result<(), Error> {
+    let file = BufWriter::new(fs::File::create(path)?);
+    let mut encoder = png::Encoder::new(file, capture.width, capture.height);
+    encoder.set_color(png::ColorType::RGBA);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&capture.pixels)?;
+    Ok(())
+}
+
+fn write_screenshot(screenshot: &PendingScreenshot, capture: &Capture) -> Result<(), Error> {
+    if let Some(dir) = screenshot.image.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    write_png(&screenshot.image, capture)?;
+    let metadata = ron::ser::to_string_pretty(&screenshot.metadata, PrettyConfig::default())?;
+    fs::write(screenshot.image.with_extension("ron"), metadata)?;
+    Ok(())
+}
+
+/// Turns screenshot requests, from the UI or F12 pressed outside of it, into pending screenshots
+/// and writes the read back pixels.
+#[derive(Debug, SystemDesc, new)]
+#[system_desc(name(ScreenshotSystemDesc))]
+pub struct ScreenshotSystem {
+    #[system_desc(event_channel_reader)]
+    event_reader: ReaderId<FilteredInputEvent>,
+}
+
+impl<'a> System<'a> for ScreenshotSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        Read<'a, EventChannel<FilteredInputEvent>>,
+        Write<'a, Screenshots>,
+        Read<'a, Scene>,
+        Read<'a, SceneMap>,
+        Read<'a, RenderMode>,
+        Read<'a, FixedStep>,
+        Read<'a, ActiveCamera>,
+        Entities<'a>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, AutoFov>,
+        ReadStorage<'a, Light>,
+        ReadStorage<'a, Named>,
+    );
+
+    fn run(
+        &mut self,
+        (events, mut screenshots, scene, scene_map, mode, fixed_step, camera, entities, transforms, auto_fovs, lights, names): Self::SystemData,
+    ) {
+        if let Some(capture) = screenshots.captured.take() {
+            if let Some(screenshot) = screenshots.pending.take() {
+                screenshots.status = match write_screenshot(&screenshot, &capture) {
+                    Ok(()) => format!("Saved {}.", screenshot.image.display()),
+                    Err(e) => format!("Failed to save {}: {}", screenshot.image.display(), e),
+                };
+            }
+        }
+
+        for event in events.read(&mut self.event_reader) {
+            if let FilteredInputEvent::Free(InputEvent::KeyPressed {
+                key_code: VirtualKeyCode::F12,
+                ..
+            }) = *event
+            {
+                screenshots.request = true;
+            }
+        }
+
+        if !screenshots.request || screenshots.is_pending() {
+            return;
+        }
+        screenshots.request = false;
+
+        let scene_name = scene
+            .scene
+            .and_then(|index| scene_map.get(index))
+            .map_or_else(|| "scene".to_string(), |entry| entry.info.name.clone());
+        let camera = camera
+            .entity
+            .and_then(|camera| transforms.get(camera).map(|transform| CameraView::capture(transform, auto_fovs.get(camera))));
+        let lights = (&entities, &lights, &transforms)
+            .join()
+            .map(|(entity, light, transform)| {
+                let position = transform.global_matrix().transform_point(&Point3::origin());
+                LightRecord {
+                    entity: entity.id(),
+                    name: names.get(entity).map(|name| name.name.to_string()),
+                    transform: transform.clone(),
+                    world_position: [position.x, position.y, position.z],
+                    light: light.clone(),
+                }
+            })
+            .collect();
+        let metadata = ScreenshotMetadata {
+            scene: scene_name,
+            mode: mode.name(),
+            camera,
+            lights,
+            fixed_step: if fixed_step.enabled {
+                Some((fixed_step.frame, fixed_step.elapsed()))
+            } else {
+                None
+            },
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|since| since.as_secs())
+                .unwrap_or(0),
+            build: BuildInfo::current(),
+        };
+        let image = screenshots.next_path(&metadata);
+        screenshots.pending = Some(PendingScreenshot { image, metadata });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_stem_replaces_reserved_characters() {
+        assert_eq!(file_stem("sponza-pbr-1700000000"), "sponza-pbr-1700000000");
+        assert_eq!(file_stem("../a/b:c d"), "___a_b_c_d");
+        assert_eq!(file_stem("Küche"), "K_che");
+    }
+}
@@ -0,0 +1,869 @@
+use amethyst::{
+    core::{
+        ecs::{Entities, Entity, Join, Read, ReadStorage, Write, WriteStorage},
+        math::{UnitQuaternion, Vector3},
+        Named, Parent, Transform,
+    },
+    renderer::{
+        light::{
+            area::{self, Intensity},
+            AreaLight, DirectionalLight, Light, PointLight, SpotLight, SunLight,
+        },
+        palette::Srgb,
+        ActiveCamera,
+    },
+};
+use amethyst_imgui::imgui::{self, ImStr, ImString};
+
+use crate::{
+    capture::{CaptureRequest, CaptureStatus},
+    fixed_step::FixedStep,
+    gizmo::{Gizmo, GIZMO_MODES},
+    history::{Change, Edit, EntitySnapshot, History, HistoryStep},
+    material_balls::{MaterialBallSettings, MATERIAL_BALLS},
+    orbit::{CAMERA_MODES, ORBIT},
+    prefab_data::{PrefabIndex, Scene},
+    scene_save::{SaveTarget, SceneSave},
+    light_proxy::LightProxy,
+    scenes::{self, SceneMap},
+    screenshot::Screenshots,
+};
+
+#[derive(Default, Debug, Clone)]
+pub struct UIState {
+    pub free_camera_movement: bool,
+    pub scene: usize,
+    pub show_light_shapes: bool,
+    pub show_light_proxies: bool,
+    /// Entity picked in the viewport.
+    pub selected: Option<Entity>,
+    /// `orbit::FLY` or `orbit::ORBIT`.
+    pub camera_mode: usize,
+    /// Set to move the camera so the selection fills the view.
+    pub frame_selected: bool,
+}
+
+const POINT: usize = 0;
+const DIRECTIONAL: usize = 1;
+const SPOT: usize = 2;
+const SUN: usize = 3;
+const DISK: usize = 4;
+const SPHERE: usize = 5;
+const RECTANGLE: usize = 6;
+
+pub(crate) const LIGHT_KINDS: [&str; 7] = ["Point", "Directional", "Spot", "Sun", "Disk", "Sphere", "Rect"];
+const LIGHT_UNITS: [&str; 2] = ["Power", "Luminance"];
+
+/// Editable copy of a `Light`.
+///
+/// Holds the union of all light properties so switching the kind keeps the shared ones.
+/// Punctual lights only have a single color, which maps to `diffuse_color`.
+#[derive(PartialEq, Clone, Debug, Copy)]
+pub struct LightTy {
+    kind: usize,
+    intensity: f32,
+    unit_type: usize,
+    diffuse_color: [f32; 3],
+    spec_color: [f32; 3],
+    two_sided: bool,
+    radius: f32,
+    /// Point light radius or spot light range.
+    range: f32,
+    smoothness: f32,
+    direction: [f32; 3],
+    /// Spot light cone angle in degrees.
+    angle: f32,
+    /// Sun angular radius in degrees.
+    angular_radius: f32,
+}
+
+impl Default for LightTy {
+    fn default() -> Self {
+        Self {
+            kind: POINT,
+            intensity: 1.0,
+            unit_type: 0,
+            diffuse_color: [1.0, 1.0, 1.0],
+            spec_color: [1.0, 1.0, 1.0],
+            two_sided: false,
+            radius: 1.0,
+            range: 10.0,
+            smoothness: 4.0,
+            direction: [0.0, -1.0, 0.0],
+            angle: 60.0,
+            angular_radius: 0.5,
+        }
+    }
+}
+
+fn color_to_array(color: Srgb) -> [f32; 3] {
+    [color.red, color.green, color.blue]
+}
+
+fn array_to_color(color: [f32; 3]) -> Srgb {
+    Srgb::new(color[0], color[1], color[2])
+}
+
+/// Index of the kind of `light` into `LIGHT_KINDS`.
+pub(crate) fn light_kind(light: &Light) -> usize {
+    match *light {
+        Light::Point(_) => POINT,
+        Light::Directional(_) => DIRECTIONAL,
+        Light::Spot(_) => SPOT,
+        Light::Sun(_) => SUN,
+        Light::Area(AreaLight::Disk(_)) => DISK,
+        Light::Area(AreaLight::Sphere(_)) => SPHERE,
+        Light::Area(AreaLight::Rectangle(_)) => RECTANGLE,
+    }
+}
+
+impl LightTy {
+    pub fn from_light(light: &Light) -> Self {
+        let mut ty = Self {
+            kind: light_kind(light),
+            ..Self::default()
+        };
+        match *light {
+            Light::Point(ref l) => {
+                ty.set_punctual(l.color, l.intensity);
+                ty.range = l.radius;
+                ty.smoothness = l.smoothness;
+            }
+            Light::Directional(ref l) => {
+                ty.set_punctual(l.color, l.intensity);
+                ty.direction = [l.direction.x, l.direction.y, l.direction.z];
+            }
+            Light::Spot(ref l) => {
+                ty.set_punctual(l.color, l.intensity);
+                ty.direction = [l.direction.x, l.direction.y, l.direction.z];
+                ty.angle = l.angle.to_degrees();
+                ty.range = l.range;
+                ty.smoothness = l.smoothness;
+            }
+            Light::Sun(ref l) => {
+                ty.set_punctual(l.color, l.intensity);
+                ty.direction = [l.direction.x, l.direction.y, l.direction.z];
+                ty.angular_radius = l.ang_rad.to_degrees();
+            }
+            Light::Area(ref light) => {
+                let (intensity, diffuse_color, spec_color) = match *light {
+                    AreaLight::Disk(ref l) => {
+                        ty.two_sided = l.two_sided;
+                        (&l.intensity, l.diffuse_color, l.spec_color)
+                    }
+                    AreaLight::Sphere(ref l) => {
+                        ty.radius = l.radius;
+                        (&l.intensity, l.diffuse_color, l.spec_color)
+                    }
+                    AreaLight::Rectangle(ref l) => {
+                        ty.two_sided = l.two_sided;
+                        (&l.intensity, l.diffuse_color, l.spec_color)
+                    }
+                };
+                ty.diffuse_color = color_to_array(diffuse_color);
+                ty.spec_color = color_to_array(spec_color);
+                match *intensity {
+                    Intensity::Power(power) => {
+                        ty.unit_type = 0;
+                        ty.intensity = power;
+                    }
+                    Intensity::Luminance(luminance) => {
+                        ty.unit_type = 1;
+                        ty.intensity = luminance;
+                    }
+                }
+            }
+        }
+        ty
+    }
+
+    fn set_punctual(&mut self, color: Srgb, intensity: f32) {
+        self.diffuse_color = color_to_array(color);
+        self.spec_color = self.diffuse_color;
+        self.intensity = intensity;
+    }
+
+    pub fn is_area(&self) -> bool {
+        self.kind >= DISK
+    }
+
+    fn intensity(&self) -> Intensity {
+        if self.unit_type == 1 {
+            Intensity::Luminance(self.intensity)
+        } else {
+            Intensity::Power(self.intensity)
+        }
+    }
+
+    fn direction(&self) -> Vector3<f32> {
+        let direction = Vector3::from(self.direction);
+        if direction.norm_squared() > 0.0 {
+            direction.normalize()
+        } else {
+            -Vector3::y()
+        }
+    }
+
+    /// Builds the light described by this state, `None` if the kind is not editable.
+    pub fn to_light(&self) -> Option<Light> {
+        let color = array_to_color(self.diffuse_color);
+        let spec_color = array_to_color(self.spec_color);
+        let light = match self.kind {
+            POINT => Light::Point(PointLight {
+                color,
+                intensity: self.intensity,
+                radius: self.range,
+                smoothness: self.smoothness,
+                ..Default::default()
+            }),
+            DIRECTIONAL => Light::Directional(DirectionalLight {
+                color,
+                intensity: self.intensity,
+                direction: self.direction(),
+                ..Default::default()
+            }),
+            SPOT => Light::Spot(SpotLight {
+                angle: self.angle.to_radians(),
+                color,
+                direction: self.direction(),
+                intensity: self.intensity,
+                range: self.range,
+                smoothness: self.smoothness,
+                ..Default::default()
+            }),
+            SUN => Light::Sun(SunLight {
+                ang_rad: self.angular_radius.to_radians(),
+                color,
+                direction: self.direction(),
+                intensity: self.intensity,
+                ..Default::default()
+            }),
+            DISK => Light::Area(AreaLight::Disk(area::Disk {
+                intensity: self.intensity(),
+                diffuse_color: color,
+                spec_color,
+                two_sided: self.two_sided,
+                ..Default::default()
+            })),
+            SPHERE => Light::Area(AreaLight::Sphere(area::Sphere {
+                intensity: self.intensity(),
+                diffuse_color: color,
+                spec_color,
+                radius: self.radius,
+                ..Default::default()
+            })),
+            RECTANGLE => Light::Area(AreaLight::Rectangle(area::Rectangle {
+                intensity: self.intensity(),
+                diffuse_color: color,
+                spec_color,
+                two_sided: self.two_sided,
+                ..Default::default()
+            })),
+            _ => return None,
+        };
+        Some(light)
+    }
+}
+
+/// Converts a rotation into yaw (around Y), pitch (around X) and roll (around Z) in degrees.
+///
+/// The angles are applied in that order, see `rotation_from_euler`.
+pub fn euler_from_rotation(rotation: &UnitQuaternion<f32>) -> [f32; 3] {
+    let m = rotation.to_rotation_matrix();
+    let m = m.matrix();
+    let pitch = (-m[(1, 2)]).max(-1.0).min(1.0).asin();
+    let (yaw, roll) = if m[(1, 2)].abs() < 0.9999 {
+        (m[(0, 2)].atan2(m[(2, 2)]), m[(1, 0)].atan2(m[(1, 1)]))
+    } else {
+        // Gimbal lock, attribute all rotation around the vertical axis to yaw.
+        ((-m[(2, 0)]).atan2(m[(0, 0)]), 0.0)
+    };
+    [yaw.to_degrees(), pitch.to_degrees(), roll.to_degrees()]
+}
+
+pub fn rotation_from_euler(angles: [f32; 3]) -> UnitQuaternion<f32> {
+    UnitQuaternion::from_axis_angle(&Vector3::y_axis(), angles[0].to_radians())
+        * UnitQuaternion::from_axis_angle(&Vector3::x_axis(), angles[1].to_radians())
+        * UnitQuaternion::from_axis_angle(&Vector3::z_axis(), angles[2].to_radians())
+}
+
+/// Rotates `transform` so its forward axis (-Z) points at `target`.
+pub fn look_at(transform: &mut Transform, target: Vector3<f32>) {
+    let direction = transform.translation() - target;
+    if direction.norm_squared() < std::f32::EPSILON {
+        return;
+    }
+    let up = if direction.normalize().y.abs() > 0.999 {
+        Vector3::z()
+    } else {
+        Vector3::y()
+    };
+    transform.face_towards(target, up);
+}
+
+#[derive(PartialEq, Clone, Debug, Copy)]
+pub struct LightSync {
+    entity: Entity,
+    translation: [f32; 3],
+    /// Yaw, pitch and roll in degrees.
+    rotation: [f32; 3],
+    scale: [f32; 2],
+    light: LightTy,
+}
+
+/// Structural changes requested from a light's panel.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum LightAction {
+    Delete,
+    Duplicate,
+    LookAt([f32; 3]),
+    PointAtCamera,
+}
+
+/// Distance in front of the camera at which new lights are placed.
+const NEW_LIGHT_DISTANCE: f32 = 3.0;
+
+pub struct UISystem {
+    variant_name: ImString,
+    new_light_kind: usize,
+    look_at_target: [f32; 3],
+    last_selected: Option<Entity>,
+}
+
+impl Default for UISystem {
+    fn default() -> Self {
+        Self {
+            variant_name: ImString::with_capacity(64),
+            new_light_kind: RECTANGLE,
+            look_at_target: [0.0, 0.0, 0.0],
+            last_selected: None,
+        }
+    }
+}
+
+impl<'s> amethyst::ecs::System<'s> for UISystem {
+    type SystemData = (
+        Write<'s, UIState>,
+        Read<'s, SceneMap>,
+        Write<'s, CaptureRequest>,
+        Read<'s, CaptureStatus>,
+        Write<'s, SceneSave>,
+        Write<'s, Gizmo>,
+        Write<'s, MaterialBallSettings>,
+        Read<'s, Scene>,
+        Read<'s, ActiveCamera>,
+        Entities<'s>,
+        WriteStorage<'s, Transform>,
+        WriteStorage<'s, Light>,
+        WriteStorage<'s, Parent>,
+        ReadStorage<'s, PrefabIndex>,
+        ReadStorage<'s, Named>,
+        ReadStorage<'s, LightProxy>,
+        Write<'s, History>,
+        Write<'s, FixedStep>,
+        Write<'s, Screenshots>,
+    );
+
+    fn run(&mut self, (mut state, scene_map, mut capture_request, capture_status, mut scene_save, mut gizmo, mut material_balls, scene, camera, entities, mut transforms, mut lights, mut parents, prefab_indices, names, proxies, mut history, mut fixed_step, mut screenshots): Self::SystemData) {
+        use amethyst_imgui::imgui::*;
+        let mut lights_cache = (&*entities, &transforms, &lights).join().map(|(e, t, l)| {
+            let trans = t.translation();
+
+            let scale = t.scale();
+            LightSync {
+                entity: e,
+                translation: [trans[0], trans[1], trans[2]],
+                rotation: euler_from_rotation(t.rotation()),
+                scale: [scale[0], scale[1]],
+                light: LightTy::from_light(l),
+            }
+        }).collect::<Vec<_>>();
+        let lights_ref = lights_cache.clone();
+        let scene_names = scene_map
+            .iter()
+            .map(|entry| ImString::new(entry.info.display_name.as_str()))
+            .collect::<Vec<_>>();
+        let scene_names = scene_names.iter().map(|name| name.as_ref()).collect::<Vec<&ImStr>>();
+        let light_kinds = combo_items(&LIGHT_KINDS);
+        let light_kinds = light_kinds.iter().map(|kind| kind.as_ref()).collect::<Vec<&ImStr>>();
+        let gizmo_modes = combo_items(&GIZMO_MODES);
+        let gizmo_modes = gizmo_modes.iter().map(|mode| mode.as_ref()).collect::<Vec<&ImStr>>();
+        let camera_modes = combo_items(&CAMERA_MODES);
+        let camera_modes = camera_modes.iter().map(|mode| mode.as_ref()).collect::<Vec<&ImStr>>();
+        let mut add_light = false;
+        let mut actions = Vec::new();
+        let selected = state.selected;
+        let selection_changed = selected != self.last_selected;
+        self.last_selected = selected;
+
+        amethyst_imgui::with(|ui| {
+            Window::new(im_str!("Lighting Example"))
+                .size([300.0, 100.0], Condition::FirstUseEver)
+                .build(ui, || {
+                    ComboBox::new(im_str!("Camera")).build_simple_string(ui,
+                        &mut state.camera_mode,
+                        &camera_modes);
+                    if state.camera_mode == ORBIT {
+                        ui.text_disabled(im_str!("Left drag rotates, middle drag pans, wheel dollies."));
+                    } else {
+                        ui.checkbox(im_str!("Free camera movement"), &mut state.free_camera_movement);
+                    }
+                    if state.selected.is_some() && ui.small_button(im_str!("Frame selected (F)")) {
+                        state.frame_selected = true;
+                    }
+                    ui.checkbox(im_str!("Show light shapes"), &mut state.show_light_shapes);
+                    ui.checkbox(im_str!("Show light surfaces"), &mut state.show_light_proxies);
+                    ComboBox::new(im_str!("Gizmo")).build_simple_string(ui,
+                        &mut gizmo.mode,
+                        &gizmo_modes);
+                    ui.checkbox(im_str!("Local space"), &mut gizmo.local_space);
+                    ui.same_line(0.0);
+                    ui.checkbox(im_str!("Snap"), &mut gizmo.snap);
+                    if gizmo.snap {
+                        ui.input_float(im_str!("Grid"), &mut gizmo.snap_translation).build();
+                        ui.input_float(im_str!("Angle step"), &mut gizmo.snap_angle).build();
+                        ui.input_float(im_str!("Scale step"), &mut gizmo.snap_scale).build();
+                    }
+                    ui.checkbox(im_str!("Fixed time step"), &mut fixed_step.enabled);
+                    if fixed_step.enabled {
+                        let mut rate = 1.0 / fixed_step.delta;
+                        if ui.input_float(im_str!("Steps per second"), &mut rate).build() && rate > 0.0 {
+                            fixed_step.delta = 1.0 / rate;
+                        }
+                        ui.text_disabled(&im_str!("Frame {}, {:.3} s", fixed_step.frame, fixed_step.elapsed()));
+                    }
+                    if capture_status.available {
+                        if ui.small_button(im_str!("Capture frame (F9)")) {
+                            capture_request.frame = true;
+                        }
+                    }
+                    ui.text_disabled(&ImString::new(capture_status.message.as_str()));
+                    if ui.small_button(im_str!("Screenshot (F12)")) {
+                        screenshots.request = true;
+                    }
+                    ui.same_line(0.0);
+                    ui.text_disabled(&im_str!("to {}", screenshots.dir.display()));
+                    if !screenshots.status.is_empty() {
+                        ui.text_disabled(&ImString::new(screenshots.status.as_str()));
+                    }
+                    // ui.label_text(im_str!("label"), im_str!("Value"));
+                    ComboBox::new(im_str!("Scene")).build_simple_string(ui,
+                        &mut state.scene,
+                        &scene_names);
+                    if let Some(entry) = scene_map.get(state.scene) {
+                        if !entry.info.description.is_empty() {
+                            ui.text_wrapped(&ImString::new(entry.info.description.as_str()));
+                        }
+                        if entry.info.name == MATERIAL_BALLS {
+                            Slider::new(im_str!("Columns (roughness)"), 1..=12).build(ui, &mut material_balls.columns);
+                            Slider::new(im_str!("Rows (metallic)"), 1..=12).build(ui, &mut material_balls.rows);
+                            ColorEdit::new(im_str!("Base albedo"), &mut material_balls.albedo).build(ui);
+                            if ui.small_button(im_str!("Regenerate")) {
+                                material_balls.regenerate = true;
+                            }
+                        }
+                    }
+                    if ui.small_button(im_str!("Save scene")) {
+                        scene_save.request = Some(SaveTarget::Overwrite);
+                    }
+                    ui.input_text(im_str!("Variant"), &mut self.variant_name).build();
+                    ui.same_line(0.0);
+                    if ui.small_button(im_str!("Save as")) && !self.variant_name.to_str().is_empty() {
+                        scene_save.request = Some(SaveTarget::Variant(self.variant_name.to_str().to_owned()));
+                    }
+                    if !scene_save.status.is_empty() {
+                        ui.text_disabled(&ImString::new(scene_save.status.as_str()));
+                    }
+                    ui.separator();
+                    if scene_map.get(state.scene).is_some() {
+                        ComboBox::new(im_str!("New light")).build_simple_string(ui,
+                            &mut self.new_light_kind,
+                            &light_kinds);
+                        ui.same_line(0.0);
+                        add_light = ui.small_button(im_str!("Add light"));
+                        ui.separator();
+                        for light in &mut lights_cache {
+                            let is_selected = selected == Some(light.entity);
+                            if let Some(action) = light_ui(ui, light, is_selected, is_selected && selection_changed, &mut self.look_at_target) {
+                                actions.push((light.entity, action));
+                            }
+                            ui.separator();
+                        }
+                    } else {
+                        ui.text(im_str!("Please select a scene!"));
+                    }
+                });
+            Window::new(im_str!("History"))
+                .size([250.0, 200.0], Condition::FirstUseEver)
+                .build(ui, || {
+                    if ui.small_button(im_str!("Undo (Ctrl+Z)")) {
+                        history.request = Some(HistoryStep::Undo);
+                    }
+                    ui.same_line(0.0);
+                    if ui.small_button(im_str!("Redo (Ctrl+Y)")) {
+                        history.request = Some(HistoryStep::Redo);
+                    }
+                    ui.separator();
+                    for command in history.undo_stack() {
+                        ui.text(&ImString::new(command.label.as_str()));
+                    }
+                    for command in history.redo_stack().iter().rev() {
+                        ui.text_disabled(&ImString::new(command.label.as_str()));
+                    }
+                });
+        });
+        for (i, light) in lights_cache.iter().enumerate() {
+            let entity = light.entity;
+            if light.translation != lights_ref[i].translation
+                || light.rotation != lights_ref[i].rotation
+                || light.scale != lights_ref[i].scale
+            {
+                let transform = transforms.get_mut(entity).unwrap();
+                let before = transform.clone();
+                if light.translation != lights_ref[i].translation {
+                    transform.set_translation_xyz(light.translation[0], light.translation[1], light.translation[2]);
+                }
+                if light.rotation != lights_ref[i].rotation {
+                    transform.set_rotation(rotation_from_euler(light.rotation));
+                }
+                if light.scale != lights_ref[i].scale {
+                    transform.set_scale(Vector3::new(light.scale[0], light.scale[1], 1.0));
+                }
+                history.record(
+                    format!("Transform light {}", entity.id()),
+                    vec![Edit::transform(entity, before, transform.clone())],
+                );
+            }
+            if light.light != lights_ref[i].light {
+                if let (Some(before), Some(after)) = (lights.get(entity).cloned(), light.light.to_light()) {
+                    lights.insert(entity, after.clone());
+                    history.record(format!("Edit light {}", entity.id()), vec![Edit::light(entity, before, after)]);
+                }
+            }
+        }
+
+        for (entity, action) in actions {
+            match action {
+                LightAction::Delete => {
+                    // Light proxies are left to `LightProxySystem`, which rebuilds them for restored lights.
+                    let mut subtree = (&entities, &transforms, !&proxies)
+                        .join()
+                        .filter(|(other, _, _)| *other == entity || scenes::is_descendant(*other, entity, &parents))
+                        .map(|(other, transform, _)| {
+                            let snapshot = EntitySnapshot {
+                                transform: transform.clone(),
+                                light: lights.get(other).cloned(),
+                                name: names.get(other).map(|name| name.name.to_string()),
+                                parent: parents.get(other).map(|p| p.entity),
+                                prefab_index: prefab_indices.get(other).cloned(),
+                            };
+                            (depth(other, &parents), other, snapshot)
+                        })
+                        .collect::<Vec<_>>();
+                    // Deepest first, so undoing, which runs backwards, recreates parents before their children.
+                    subtree.sort_by(|a, b| b.0.cmp(&a.0));
+                    for (_, other, _) in &subtree {
+                        let _ = entities.delete(*other);
+                    }
+                    history.record(
+                        format!("Delete light {}", entity.id()),
+                        subtree
+                            .into_iter()
+                            .map(|(_, other, snapshot)| Edit {
+                                entity: other,
+                                change: Change::Delete(snapshot),
+                            })
+                            .collect(),
+                    );
+                }
+                LightAction::LookAt(target) => {
+                    let edits = aim_light_recorded(entity, Vector3::from(target), &mut transforms, &mut lights);
+                    history.record(format!("Aim light {}", entity.id()), edits);
+                }
+                LightAction::PointAtCamera => {
+                    let target = camera.entity.and_then(|camera| transforms.get(camera)).map(|t| *t.translation());
+                    if let Some(target) = target {
+                        let edits = aim_light_recorded(entity, target, &mut transforms, &mut lights);
+                        history.record(format!("Aim light {}", entity.id()), edits);
+                    }
+                }
+                LightAction::Duplicate => {
+                    if let (Some(transform), Some(light)) = (transforms.get(entity), lights.get(entity)) {
+                        let mut transform = transform.clone();
+                        transform.append_translation_xyz(0.5, 0.0, 0.0);
+                        let snapshot = EntitySnapshot {
+                            transform,
+                            light: Some(light.clone()),
+                            name: None,
+                            parent: parents.get(entity).map(|p| p.entity),
+                            prefab_index: None,
+                        };
+                        let duplicate = spawn_light(&snapshot, &entities, &mut transforms, &mut lights, &mut parents);
+                        history.record(
+                            format!("Duplicate light {}", entity.id()),
+                            vec![Edit {
+                                entity: duplicate,
+                                change: Change::Create(snapshot),
+                            }],
+                        );
+                    }
+                }
+            }
+        }
+
+        if add_light {
+            let new_light = LightTy {
+                kind: self.new_light_kind,
+                ..Default::default()
+            };
+            if let (Some(root), Some(light)) = (scene.entity, new_light.to_light()) {
+                let mut transform = Transform::default();
+                if let Some(camera_transform) = camera.entity.and_then(|camera| transforms.get(camera)) {
+                    let forward = camera_transform.rotation() * -Vector3::z();
+                    transform.set_translation(camera_transform.translation() + forward * NEW_LIGHT_DISTANCE);
+                    transform.set_rotation(*camera_transform.rotation());
+                }
+                let snapshot = EntitySnapshot {
+                    transform,
+                    light: Some(light),
+                    name: None,
+                    parent: Some(root),
+                    prefab_index: None,
+                };
+                let entity = spawn_light(&snapshot, &entities, &mut transforms, &mut lights, &mut parents);
+                history.record(
+                    format!("Add {} light", LIGHT_KINDS[self.new_light_kind]),
+                    vec![Edit {
+                        entity,
+                        change: Change::Create(snapshot),
+                    }],
+                );
+            }
+        }
+    }
+}
+
+/// Number of ancestors of `entity`.
+fn depth(entity: Entity, parents: &WriteStorage<'_, Parent>) -> usize {
+    std::iter::successors(parents.get(entity), |parent| parents.get(parent.entity)).count()
+}
+
+fn spawn_light(
+    snapshot: &EntitySnapshot,
+    entities: &Entities<'_>,
+    transforms: &mut WriteStorage<'_, Transform>,
+    lights: &mut WriteStorage<'_, Light>,
+    parents: &mut WriteStorage<'_, Parent>,
+) -> Entity {
+    let mut builder = entities.build_entity().with(snapshot.transform.clone(), transforms);
+    if let Some(ref light) = snapshot.light {
+        builder = builder.with(light.clone(), lights);
+    }
+    if let Some(parent) = snapshot.parent {
+        builder = builder.with(Parent { entity: parent }, parents);
+    }
+    builder.build()
+}
+
+/// Like `aim_light`, returning the changes for the history.
+fn aim_light_recorded(
+    entity: Entity,
+    target: Vector3<f32>,
+    transforms: &mut WriteStorage<'_, Transform>,
+    lights: &mut WriteStorage<'_, Light>,
+) -> Vec<Edit> {
+    let transform = transforms.get(entity).cloned();
+    let light = lights.get(entity).cloned();
+    aim_light(entity, target, transforms, lights);
+    let mut edits = Vec::new();
+    if let (Some(before), Some(after)) = (transform, transforms.get(entity)) {
+        edits.push(Edit::transform(entity, before, after.clone()));
+    }
+    if let (Some(before), Some(after)) = (light, lights.get(entity)) {
+        edits.push(Edit::light(entity, before, after.clone()));
+    }
+    edits
+}
+
+/// Points the light at `target`, updating the direction of lights that carry one as well.
+fn aim_light(
+    entity: Entity,
+    target: Vector3<f32>,
+    transforms: &mut WriteStorage<'_, Transform>,
+    lights: &mut WriteStorage<'_, Light>,
+) {
+    let transform = match transforms.get_mut(entity) {
+        Some(transform) => transform,
+        None => return,
+    };
+    look_at(transform, target);
+    let direction = transform.rotation() * -Vector3::z();
+    match lights.get_mut(entity) {
+        Some(Light::Directional(ref mut l)) => l.direction = direction,
+        Some(Light::Spot(ref mut l)) => l.direction = direction,
+        Some(Light::Sun(ref mut l)) => l.direction = direction,
+        _ => {}
+    }
+}
+
+fn combo_items(items: &[&str]) -> Vec<ImString> {
+    items.iter().map(|item| ImString::new(*item)).collect()
+}
+
+/// Draws the panel of a single light; `reveal` opens it, e.g. after the light was picked.
+fn light_ui(
+    ui: &imgui::Ui,
+    light: &mut LightSync,
+    selected: bool,
+    reveal: bool,
+    look_at_target: &mut [f32; 3],
+) -> Option<LightAction> {
+    use amethyst_imgui::imgui::*;
+    let mut action = None;
+    let id = light.entity.id();
+    let label = if selected {
+        im_str!("Light: {} (selected)###light{}", id, id)
+    } else {
+        im_str!("Light: {}###light{}", id, id)
+    };
+    let mut node = ui.tree_node(&label);
+    if reveal {
+        node = node.opened(true, Condition::Always);
+    }
+    node.build(|| {
+        if ui.small_button(im_str!("Duplicate")) {
+            action = Some(LightAction::Duplicate);
+        }
+        ui.same_line(0.0);
+        if ui.small_button(im_str!("Delete")) {
+            action = Some(LightAction::Delete);
+        }
+        let translation = ui.push_id("translation");
+        // Translation
+        {
+            ui.drag_float3(im_str!("Position"), &mut light.translation).speed(0.05).build();
+        }
+        translation.pop(ui);
+        let rot = ui.push_id("rot");
+        // Rotation
+        {
+            Slider::new(im_str!("Yaw"), -180.0..=180.0).build(ui, &mut light.rotation[0]);
+            Slider::new(im_str!("Pitch"), -90.0..=90.0).build(ui, &mut light.rotation[1]);
+            Slider::new(im_str!("Roll"), -180.0..=180.0).build(ui, &mut light.rotation[2]);
+            ui.input_float3(im_str!("Target"), look_at_target).build();
+            if ui.small_button(im_str!("Look at target")) {
+                action = Some(LightAction::LookAt(*look_at_target));
+            }
+            ui.same_line(0.0);
+            if ui.small_button(im_str!("Point at camera")) {
+                action = Some(LightAction::PointAtCamera);
+            }
+        }
+        rot.pop(ui);
+        let scale = ui.push_id("scale");
+        // Rotation
+        {
+            Slider::new(im_str!("Scale X"), 0.0..=10.0).build(ui, &mut light.scale[0]);
+            Slider::new(im_str!("Scale Y"), 0.0..=10.0).build(ui, &mut light.scale[1]);
+        }
+        scale.pop(ui);
+        ui.separator();
+        light_properties_ui(ui, &mut light.light);
+    });
+    action
+}
+
+/// Draws the kind, intensity, color and per-kind settings of a light.
+pub fn light_properties_ui(ui: &imgui::Ui, light: &mut LightTy) {
+    use amethyst_imgui::imgui::*;
+    let kinds = combo_items(&LIGHT_KINDS);
+    let kinds = kinds.iter().map(|kind| kind.as_ref()).collect::<Vec<&ImStr>>();
+    ComboBox::new(im_str!("Kind")).build_simple_string(ui,
+        &mut light.kind,
+        &kinds);
+    if light.is_area() {
+        let units = combo_items(&LIGHT_UNITS);
+        let units = units.iter().map(|unit| unit.as_ref()).collect::<Vec<&ImStr>>();
+        ComboBox::new(im_str!("Light Unit")).build_simple_string(ui,
+            &mut light.unit_type,
+            &units);
+        if light.unit_type == 0 {
+            Slider::new(im_str!("Power"), 0.0..=100.0).build(ui, &mut light.intensity);
+        } else if light.unit_type == 1 {
+            Slider::new(im_str!("Luminance"), 0.0..=100.0).build(ui, &mut light.intensity);
+        }
+        ColorEdit::new(im_str!("Diffuse color"), &mut light.diffuse_color).build(ui);
+        ColorEdit::new(im_str!("Specular color"), &mut light.spec_color).build(ui);
+    } else {
+        Slider::new(im_str!("Intensity"), 0.0..=100.0).build(ui, &mut light.intensity);
+        ColorEdit::new(im_str!("Color"), &mut light.diffuse_color).build(ui);
+    }
+    match light.kind {
+        POINT => {
+            Slider::new(im_str!("Radius"), 0.0..=100.0).build(ui, &mut light.range);
+            Slider::new(im_str!("Smoothness"), 0.0..=10.0).build(ui, &mut light.smoothness);
+        }
+        DIRECTIONAL | SUN => {
+            direction_ui(ui, &mut light.direction);
+            if light.kind == SUN {
+                Slider::new(im_str!("Angular radius"), 0.0..=5.0).build(ui, &mut light.angular_radius);
+            }
+        }
+        SPOT => {
+            direction_ui(ui, &mut light.direction);
+            Slider::new(im_str!("Angle"), 0.0..=180.0).build(ui, &mut light.angle);
+            Slider::new(im_str!("Range"), 0.0..=100.0).build(ui, &mut light.range);
+            Slider::new(im_str!("Smoothness"), 0.0..=10.0).build(ui, &mut light.smoothness);
+        }
+        DISK | RECTANGLE => {
+            ui.checkbox(im_str!("Two sided"), &mut light.two_sided);
+        }
+        SPHERE => {
+            Slider::new(im_str!("Radius"), 0.0..=10.0).build(ui, &mut light.radius);
+        }
+        _ => {}
+    }
+}
+
+fn direction_ui(ui: &imgui::Ui, direction: &mut [f32; 3]) {
+    use amethyst_imgui::imgui::*;
+    let id = ui.push_id("direction");
+    Slider::new(im_str!("Dir X"), -1.0..=1.0).build(ui, &mut direction[0]);
+    Slider::new(im_str!("Dir Y"), -1.0..=1.0).build(ui, &mut direction[1]);
+    Slider::new(im_str!("Dir Z"), -1.0..=1.0).build(ui, &mut direction[2]);
+    id.pop(ui);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn euler_angles_round_trip() {
+        for &yaw in &[-170.0, -90.0, -30.0, 0.0, 45.0, 120.0, 179.0] {
+            for &pitch in &[-80.0, -45.0, 0.0, 10.0, 60.0, 85.0] {
+                for &roll in &[-150.0, -20.0, 0.0, 35.0, 90.0] {
+                    let angles = euler_from_rotation(&rotation_from_euler([yaw, pitch, roll]));
+                    for (actual, expected) in angles.iter().zip(&[yaw, pitch, roll]) {
+                        assert!(
+                            (actual - expected).abs() < 1.0e-2,
+                            "{:?} came back as {:?}",
+                            [yaw, pitch, roll],
+                            angles
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn gimbal_lock_keeps_the_rotation() {
+        for &pitch in &[-90.0, 90.0] {
+            let rotation = rotation_from_euler([30.0, pitch, 20.0]);
+            let angles = euler_from_rotation(&rotation);
+            assert!(angles[2].abs() < 1.0e-6);
+            assert!(rotation_from_euler(angles).angle_to(&rotation) < 1.0e-2);
+        }
+    }
+}